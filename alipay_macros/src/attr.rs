@@ -0,0 +1,287 @@
+use syn::{spanned::Spanned, Attribute, Error, Lit, Meta, MetaList, NestedMeta, Result};
+
+/// 字段上的`#[alipay(...)]`属性
+#[derive(Default)]
+pub struct FieldAttr {
+    pub rename: Option<String>,
+    pub skip: bool,
+    pub skip_if_none: bool,
+    pub flatten: bool,
+    pub max_len: Option<usize>,
+    pub range: Option<(Option<f64>, Option<f64>)>,
+    pub pattern: Option<String>,
+    pub required: bool,
+}
+
+/// 枚举成员上的`#[alipay(...)]`属性
+#[derive(Default)]
+pub struct VariantAttr {
+    pub rename: Option<String>,
+}
+
+/// 结构体或枚举上的`#[alipay(...)]`属性
+#[derive(Default)]
+pub struct ContainerAttr {
+    pub rename_all: Option<RenameRule>,
+    pub tag: Option<String>,
+    /// 同时实现AlipayParamsRef
+    pub by_ref: bool,
+}
+
+#[derive(Clone, Copy)]
+pub enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn from_lit(lit: &Lit) -> Result<Self> {
+        match lit_str(lit, "rename_all")?.as_str() {
+            "lowercase" => Ok(RenameRule::Lower),
+            "UPPERCASE" => Ok(RenameRule::Upper),
+            "PascalCase" => Ok(RenameRule::Pascal),
+            "camelCase" => Ok(RenameRule::Camel),
+            "snake_case" => Ok(RenameRule::Snake),
+            "SCREAMING_SNAKE_CASE" => Ok(RenameRule::ScreamingSnake),
+            "kebab-case" => Ok(RenameRule::Kebab),
+            "SCREAMING-KEBAB-CASE" => Ok(RenameRule::ScreamingKebab),
+            rule => Err(Error::new_spanned(
+                lit,
+                format!("unknown rename rule: {}", rule),
+            )),
+        }
+    }
+
+    /// 转换snake_case的字段名
+    pub fn apply_to_field(&self, field: &str) -> String {
+        match self {
+            RenameRule::Lower | RenameRule::Snake => field.to_owned(),
+            RenameRule::Upper | RenameRule::ScreamingSnake => field.to_ascii_uppercase(),
+            RenameRule::Pascal => field
+                .split('_')
+                .map(|word| {
+                    let mut chars = word.chars();
+                    match chars.next() {
+                        Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                        None => String::new(),
+                    }
+                })
+                .collect(),
+            RenameRule::Camel => {
+                let pascal = RenameRule::Pascal.apply_to_field(field);
+                let mut chars = pascal.chars();
+                match chars.next() {
+                    Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+                    None => String::new(),
+                }
+            }
+            RenameRule::Kebab => field.replace('_', "-"),
+            RenameRule::ScreamingKebab => field.replace('_', "-").to_ascii_uppercase(),
+        }
+    }
+
+    /// 转换PascalCase的枚举成员名
+    pub fn apply_to_variant(&self, variant: &str) -> String {
+        match self {
+            RenameRule::Pascal => variant.to_owned(),
+            RenameRule::Lower => variant.to_ascii_lowercase(),
+            RenameRule::Upper => variant.to_ascii_uppercase(),
+            RenameRule::Camel => {
+                let mut chars = variant.chars();
+                match chars.next() {
+                    Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+                    None => String::new(),
+                }
+            }
+            RenameRule::Snake => {
+                let mut snake = String::new();
+                for (i, ch) in variant.char_indices() {
+                    if i > 0 && ch.is_uppercase() {
+                        snake.push('_');
+                    }
+                    snake.push(ch.to_ascii_lowercase());
+                }
+                snake
+            }
+            RenameRule::ScreamingSnake => RenameRule::Snake
+                .apply_to_variant(variant)
+                .to_ascii_uppercase(),
+            RenameRule::Kebab => RenameRule::Snake
+                .apply_to_variant(variant)
+                .replace('_', "-"),
+            RenameRule::ScreamingKebab => RenameRule::ScreamingSnake
+                .apply_to_variant(variant)
+                .replace('_', "-"),
+        }
+    }
+}
+
+fn alipay_metas(attrs: &[Attribute]) -> Result<Vec<NestedMeta>> {
+    let mut metas = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("alipay")) {
+        match attr.parse_meta()? {
+            Meta::List(list) => metas.extend(list.nested),
+            meta => return Err(Error::new_spanned(meta, "expected #[alipay(...)]")),
+        }
+    }
+    Ok(metas)
+}
+
+fn lit_f64(lit: &Lit, name: &str) -> Result<f64> {
+    match lit {
+        Lit::Int(i) => i.base10_parse(),
+        Lit::Float(f) => f.base10_parse(),
+        _ => Err(Error::new_spanned(
+            lit,
+            format!("{} must be a number", name),
+        )),
+    }
+}
+
+/// 解析`range(min = 0.01, max = 100000000)`，min和max至少要有一个
+fn parse_range(list: &MetaList) -> Result<(Option<f64>, Option<f64>)> {
+    let mut range = (None, None);
+    for meta in list.nested.iter() {
+        match meta {
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("min") => {
+                range.0 = Some(lit_f64(&nv.lit, "min")?);
+            }
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("max") => {
+                range.1 = Some(lit_f64(&nv.lit, "max")?);
+            }
+            meta => return Err(Error::new_spanned(meta, "expected min = ... or max = ...")),
+        }
+    }
+    if range.0.is_none() && range.1.is_none() {
+        return Err(Error::new_spanned(list, "range requires min or max"));
+    }
+    Ok(range)
+}
+
+fn lit_str(lit: &Lit, name: &str) -> Result<String> {
+    match lit {
+        Lit::Str(s) => Ok(s.value()),
+        _ => Err(Error::new_spanned(
+            lit,
+            format!("{} must be a string literal", name),
+        )),
+    }
+}
+
+impl FieldAttr {
+    pub fn from_attrs(attrs: &[Attribute]) -> Result<Self> {
+        let mut field_attr = FieldAttr::default();
+        for meta in alipay_metas(attrs)? {
+            match meta {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => {
+                    field_attr.skip = true;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip_if_none") => {
+                    field_attr.skip_if_none = true;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("flatten") => {
+                    field_attr.flatten = true;
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("rename") => {
+                    field_attr.rename = Some(lit_str(&nv.lit, "rename")?);
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("required") => {
+                    field_attr.required = true;
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("max_len") => {
+                    field_attr.max_len = Some(match &nv.lit {
+                        Lit::Int(i) => i.base10_parse()?,
+                        lit => return Err(Error::new_spanned(lit, "max_len must be an integer")),
+                    });
+                }
+                NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("range") => {
+                    field_attr.range = Some(parse_range(&list)?);
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("pattern") => {
+                    let pattern = lit_str(&nv.lit, "pattern")?;
+                    if let Err(err) = regex::Regex::new(&pattern) {
+                        return Err(Error::new_spanned(&nv.lit, err));
+                    }
+                    field_attr.pattern = Some(pattern);
+                }
+                meta => return Err(Error::new_spanned(meta, "unknown alipay field attribute")),
+            }
+        }
+        let message = if field_attr.flatten && field_attr.rename.is_some() {
+            "flatten field cannot be renamed"
+        } else if field_attr.flatten && field_attr.has_rules() {
+            "flatten field cannot have validation rules"
+        } else if field_attr.skip && field_attr.has_rules() {
+            "skipped field cannot have validation rules"
+        } else {
+            return Ok(field_attr);
+        };
+        let span = attrs
+            .iter()
+            .find(|attr| attr.path.is_ident("alipay"))
+            .map(|attr| attr.span())
+            .unwrap_or_else(proc_macro2::Span::call_site);
+        Err(Error::new(span, message))
+    }
+
+    /// 字段是否没有任何属性，tuple struct的字段不支持属性
+    pub fn is_empty(&self) -> bool {
+        self.rename.is_none()
+            && !self.skip
+            && !self.skip_if_none
+            && !self.flatten
+            && !self.has_rules()
+    }
+
+    /// 是否有校验规则
+    pub fn has_rules(&self) -> bool {
+        self.max_len.is_some() || self.range.is_some() || self.pattern.is_some() || self.required
+    }
+}
+
+impl VariantAttr {
+    pub fn from_attrs(attrs: &[Attribute]) -> Result<Self> {
+        let mut variant_attr = VariantAttr::default();
+        for meta in alipay_metas(attrs)? {
+            match meta {
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("rename") => {
+                    variant_attr.rename = Some(lit_str(&nv.lit, "rename")?);
+                }
+                meta => return Err(Error::new_spanned(meta, "unknown alipay variant attribute")),
+            }
+        }
+        Ok(variant_attr)
+    }
+}
+
+impl ContainerAttr {
+    pub fn from_attrs(attrs: &[Attribute]) -> Result<Self> {
+        let mut container_attr = ContainerAttr::default();
+        for meta in alipay_metas(attrs)? {
+            match meta {
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("rename_all") => {
+                    container_attr.rename_all = Some(RenameRule::from_lit(&nv.lit)?);
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("tag") => {
+                    container_attr.tag = Some(lit_str(&nv.lit, "tag")?);
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("by_ref") => {
+                    container_attr.by_ref = true;
+                }
+                meta => {
+                    return Err(Error::new_spanned(
+                        meta,
+                        "unknown alipay container attribute",
+                    ))
+                }
+            }
+        }
+        Ok(container_attr)
+    }
+}
@@ -1,52 +1,501 @@
-mod error;
-use error::Error;
+mod attr;
+use attr::{ContainerAttr, FieldAttr, RenameRule, VariantAttr};
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, Data, DeriveInput, Fields};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{
+    ext::IdentExt, parse_macro_input, spanned::Spanned, Data, DataEnum, DataStruct, DeriveInput,
+    Error, Field, Fields, FieldsNamed, FieldsUnnamed, Result,
+};
 
-#[proc_macro_derive(AlipayParams)]
+/// 为结构体和枚举实现AlipayParams，字段的类型需要实现AlipayParams
+///
+/// 设置`#[alipay(by_ref)]`后还会实现AlipayParamsRef，这时字段的类型也需要实现AlipayParamsRef。
+/// 总是转换为对象的类型（具名字段的结构体、带标签或者没有unit成员的枚举）还会实现AlipayObject。
+///
+/// - 具名字段的结构体转换为对象
+/// - 只有一个字段的tuple struct转换为字段的值，多个字段的转换为数组，unit struct转换为Null
+/// - 只有unit成员的枚举转换为成员名字符串
+/// - 带数据的枚举默认转换为`{"成员名": 值}`，
+///   设置`#[alipay(tag = "type")]`后转换为`{"type": "成员名", ...字段}`
+///
+/// 支持的属性：
+/// - `#[alipay(by_ref)]`：实现AlipayParamsRef，可以把引用作为参数
+/// - `#[alipay(rename_all = "camelCase")]`：按照规则重命名所有字段或枚举成员，
+///   规则与serde相同（lowercase、UPPERCASE、PascalCase、camelCase、snake_case、
+///   SCREAMING_SNAKE_CASE、kebab-case、SCREAMING-KEBAB-CASE）
+/// - `#[alipay(tag = "type")]`：枚举使用内部标签
+/// - `#[alipay(rename = "type")]`：重命名字段或枚举成员
+/// - `#[alipay(skip)]`：忽略字段
+/// - `#[alipay(skip_if_none)]`：字段值为None时不输出该字段
+/// - `#[alipay(flatten)]`：把字段的内容展开到当前结构体中，字段的类型需要实现AlipayObject
+///
+/// 校验属性，生成的validate会在签名前调用，嵌套的字段也会被校验：
+/// - `#[alipay(required)]`：字段不能是None、空字符串或空数组
+/// - `#[alipay(max_len = 64)]`：字符串的最大字符数或数组的最大长度
+/// - `#[alipay(range(min = 0.01, max = 100000000))]`：数值范围，也可以用于金额字符串和Money
+/// - `#[alipay(pattern = "^[0-9a-zA-Z_]+$")]`：字符串需要匹配的正则表达式
+#[proc_macro_derive(AlipayParams, attributes(alipay))]
 pub fn derive_alipay_param(item: TokenStream) -> TokenStream {
     let input = parse_macro_input!(item as DeriveInput);
-    impl_map_macro(&input).unwrap()
+    impl_map_macro(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
 }
 
-fn impl_map_macro(input: &syn::DeriveInput) -> Result<TokenStream, Error> {
-    let data_struct = match &input.data {
-        Data::Struct(data) => data,
-        Data::Enum(_) => return Err(Error::new("Must be struct type")),
-        Data::Union(_) => return Err(Error::new("Must be struct type")),
+fn impl_map_macro(input: &DeriveInput) -> Result<TokenStream2> {
+    let container_attr = ContainerAttr::from_attrs(&input.attrs)?;
+    let (body, body_ref, validate) = match &input.data {
+        Data::Struct(data) => (
+            struct_body(data, &container_attr, false)?,
+            struct_body(data, &container_attr, true)?,
+            struct_validate(data, &container_attr)?,
+        ),
+        // 对&Self进行match时绑定的是字段的引用，所以两个实现可以使用相同的代码
+        Data::Enum(data) => (
+            enum_body(data, &container_attr)?,
+            enum_body(data, &container_attr)?,
+            enum_validate(data, &container_attr)?,
+        ),
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "AlipayParams cannot be derived for unions",
+            ))
+        }
     };
-    let fields_named = match &data_struct.fields {
-        Fields::Named(fields_named) => fields_named,
-        Fields::Unnamed(_) => return Err(Error::new("Struct must have field")),
-        Fields::Unit => return Err(Error::new("Struct type cannot have punctuation marks")),
+    if container_attr.tag.is_some() && !matches!(input.data, Data::Enum(_)) {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "#[alipay(tag = \"...\")] is only supported on enums",
+        ));
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // 总是转换为对象的类型才可以被展开
+    let is_object = match &input.data {
+        Data::Struct(data) => matches!(data.fields, Fields::Named(_)),
+        Data::Enum(data) => {
+            container_attr.tag.is_some()
+                || (!data.variants.is_empty()
+                    && data
+                        .variants
+                        .iter()
+                        .all(|variant| !matches!(variant.fields, Fields::Unit)))
+        }
+        Data::Union(_) => false,
+    };
+    let ref_impl = if container_attr.by_ref {
+        quote! {
+            impl #impl_generics alipay_params::AlipayParamsRef for #name #ty_generics #where_clause {
+                fn to_alipay_value_ref(&self) -> alipay_params::AlipayValue {
+                    #body_ref
+                }
+            }
+        }
+    } else {
+        TokenStream2::new()
+    };
+    let object_impl = if is_object {
+        quote!(impl #impl_generics alipay_params::AlipayObject for #name #ty_generics #where_clause {})
+    } else {
+        TokenStream2::new()
     };
-    let to_field_value_token_streams: Vec<proc_macro2::TokenStream> = fields_named
-        .named
-        .iter()
-        .enumerate()
-        .map(|(i, field)| {
-            let field_name = match &field.ident {
-                Some(ident) => syn::Member::Named(ident.clone()),
-                None => syn::Member::Unnamed(i.into()),
-            };
 
+    Ok(quote! {
+        impl #impl_generics alipay_params::AlipayParams for #name #ty_generics #where_clause {
+            fn to_alipay_value(self) -> alipay_params::AlipayValue {
+                #body
+            }
+            fn validate(&self) -> ::std::result::Result<(), alipay_params::ValidationError> {
+                #validate
+                Ok(())
+            }
+        }
+
+        #ref_impl
+
+        #object_impl
+    })
+}
+
+/// by_ref为true时取字段的引用，用于AlipayParamsRef
+fn struct_body(
+    data: &DataStruct,
+    container_attr: &ContainerAttr,
+    by_ref: bool,
+) -> Result<TokenStream2> {
+    let reference = if by_ref { quote!(&) } else { quote!() };
+    match &data.fields {
+        Fields::Named(fields) => {
+            let values = fields
+                .named
+                .iter()
+                .map(|field| {
+                    let ident = field.ident.as_ref().unwrap();
+                    quote!(#reference self.#ident)
+                })
+                .collect();
+            named_fields_body(fields, values, container_attr.rename_all, None)
+        }
+        Fields::Unnamed(fields) => {
+            let values = (0..fields.unnamed.len())
+                .map(|i| {
+                    let index = syn::Index::from(i);
+                    quote!(#reference self.#index)
+                })
+                .collect();
+            unnamed_fields_body(fields, values)
+        }
+        Fields::Unit => Ok(quote!(alipay_params::AlipayValue::Null)),
+    }
+}
+
+/// 具名字段转换为对象，values是每个字段对应的取值表达式
+fn named_fields_body(
+    fields: &FieldsNamed,
+    values: Vec<TokenStream2>,
+    rename_all: Option<RenameRule>,
+    tag: Option<(&str, &str)>,
+) -> Result<TokenStream2> {
+    let mut inserts: Vec<TokenStream2> = Vec::new();
+    if let Some((tag, name)) = tag {
+        inserts.push(quote! {
+            result.insert(#tag.to_string(), alipay_params::AlipayValue::String(#name.to_string()));
+        });
+    }
+    for (field, value) in fields.named.iter().zip(values) {
+        let field_attr = FieldAttr::from_attrs(&field.attrs)?;
+        if field_attr.skip {
+            continue;
+        }
+        let field_key = field_key(field, &field_attr, rename_all);
+
+        let token_stream = if field_attr.flatten {
+            quote!(result.extend(alipay_params::__private::flatten(#value));)
+        } else if field_attr.skip_if_none {
+            quote! {
+                let value = alipay_params::AlipayParams::to_alipay_value(#value);
+                if !value.is_null() {
+                    result.insert(#field_key.to_string(), value);
+                }
+            }
+        } else {
             quote! {
-                result.insert(stringify!(#field_name).to_string(), self.#field_name.to_alipay_value());
+                result.insert(#field_key.to_string(), alipay_params::AlipayParams::to_alipay_value(#value));
             }
-        })
-        .collect();
+        };
+        inserts.push(token_stream);
+    }
+    Ok(quote! {
+        let mut result: alipay_params::IndexMap<String, alipay_params::AlipayValue> = alipay_params::IndexMap::new();
+        #(#inserts)*
+        alipay_params::AlipayValue::from(result)
+    })
+}
 
-    let struct_name = &input.ident;
-    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+fn field_key(field: &Field, field_attr: &FieldAttr, rename_all: Option<RenameRule>) -> String {
+    let field_key = field.ident.as_ref().unwrap().unraw().to_string();
+    match (&field_attr.rename, rename_all) {
+        (Some(rename), _) => rename.clone(),
+        (None, Some(rule)) => rule.apply_to_field(&field_key),
+        (None, None) => field_key,
+    }
+}
+
+fn struct_validate(data: &DataStruct, container_attr: &ContainerAttr) -> Result<TokenStream2> {
+    match &data.fields {
+        Fields::Named(fields) => {
+            let values = fields
+                .named
+                .iter()
+                .map(|field| {
+                    let ident = field.ident.as_ref().unwrap();
+                    quote!(&self.#ident)
+                })
+                .collect();
+            named_fields_validate(fields, values, container_attr.rename_all)
+        }
+        Fields::Unnamed(fields) => {
+            let values = (0..fields.unnamed.len())
+                .map(|i| {
+                    let index = syn::Index::from(i);
+                    quote!(&self.#index)
+                })
+                .collect();
+            Ok(unnamed_fields_validate(values))
+        }
+        Fields::Unit => Ok(TokenStream2::new()),
+    }
+}
 
+/// 具名字段的校验代码，values是每个字段对应的引用表达式
+fn named_fields_validate(
+    fields: &FieldsNamed,
+    values: Vec<TokenStream2>,
+    rename_all: Option<RenameRule>,
+) -> Result<TokenStream2> {
+    let mut checks: Vec<TokenStream2> = Vec::new();
+    for (field, value) in fields.named.iter().zip(values) {
+        let field_attr = FieldAttr::from_attrs(&field.attrs)?;
+        if field_attr.skip {
+            continue;
+        }
+        let field_key = field_key(field, &field_attr, rename_all);
+        let error = |rule: TokenStream2| {
+            quote! {
+                return Err(alipay_params::ValidationError::new(
+                    #field_key,
+                    alipay_params::ValidationRule::#rule,
+                ));
+            }
+        };
+
+        let mut rules: Vec<TokenStream2> = Vec::new();
+        if field_attr.required {
+            let error = error(quote!(Required));
+            rules.push(quote! {
+                if !alipay_params::ValidateField::is_present(value) {
+                    #error
+                }
+            });
+        }
+        if let Some(max_len) = field_attr.max_len {
+            let error = error(quote!(MaxLen(#max_len)));
+            rules.push(quote! {
+                if let Some(len) = alipay_params::ValidateField::validated_len(value) {
+                    if len > #max_len {
+                        #error
+                    }
+                }
+            });
+        }
+        if let Some((min, max)) = field_attr.range {
+            // 无法解析的金额字符串为NaN，校验失败
+            let out_of_range = match (min, max) {
+                (Some(min), Some(max)) => quote!(!(#min..=#max).contains(&number)),
+                (Some(min), None) => quote!(number.is_nan() || number < #min),
+                (None, Some(max)) => quote!(number.is_nan() || number > #max),
+                (None, None) => unreachable!(),
+            };
+            let min = optional_f64(min);
+            let max = optional_f64(max);
+            let error = error(quote!(Range { min: #min, max: #max }));
+            rules.push(quote! {
+                if let Some(number) = alipay_params::ValidateField::validated_number(value) {
+                    if #out_of_range {
+                        #error
+                    }
+                }
+            });
+        }
+        if let Some(pattern) = &field_attr.pattern {
+            let error = error(quote!(Pattern(#pattern.to_string())));
+            rules.push(quote! {
+                if let Some(s) = alipay_params::ValidateField::validated_str(value) {
+                    static PATTERN: ::std::sync::OnceLock<alipay_params::__private::Regex> =
+                        ::std::sync::OnceLock::new();
+                    let pattern = PATTERN
+                        .get_or_init(|| alipay_params::__private::Regex::new(#pattern).unwrap());
+                    if !pattern.is_match(s) {
+                        #error
+                    }
+                }
+            });
+        }
+        let nested = if field_attr.flatten {
+            // 类型没有实现AlipayObject时编译错误指向字段的类型
+            let ty = &field.ty;
+            let assert =
+                quote_spanned!(ty.span()=> alipay_params::__private::assert_object::<#ty>(););
+            quote! {
+                #assert
+                alipay_params::AlipayParams::validate(value)?;
+            }
+        } else {
+            quote!(alipay_params::AlipayParams::validate(value).map_err(|e| e.nested(#field_key))?;)
+        };
+        checks.push(quote! {
+            {
+                let value = #value;
+                #(#rules)*
+                #nested
+            }
+        });
+    }
+    Ok(quote!(#(#checks)*))
+}
+
+fn optional_f64(value: Option<f64>) -> TokenStream2 {
+    match value {
+        Some(value) => {
+            let value = proc_macro2::Literal::f64_suffixed(value);
+            quote!(Some(#value))
+        }
+        None => quote!(None),
+    }
+}
+
+/// tuple字段只校验嵌套的参数，多个字段时字段名为下标
+fn unnamed_fields_validate(values: Vec<TokenStream2>) -> TokenStream2 {
+    if values.len() == 1 {
+        let value = &values[0];
+        return quote!(alipay_params::AlipayParams::validate(#value)?;);
+    }
+    let checks = values.iter().enumerate().map(|(i, value)| {
+        let index = i.to_string();
+        quote!(alipay_params::AlipayParams::validate(#value).map_err(|e| e.nested(#index))?;)
+    });
+    quote!(#(#checks)*)
+}
+
+/// 一个字段时转换为字段的值，多个字段时转换为数组
+fn unnamed_fields_body(fields: &FieldsUnnamed, values: Vec<TokenStream2>) -> Result<TokenStream2> {
+    for field in fields.unnamed.iter() {
+        if !FieldAttr::from_attrs(&field.attrs)?.is_empty() {
+            return Err(Error::new_spanned(
+                field,
+                "alipay attributes are not supported on tuple fields",
+            ));
+        }
+    }
+    Ok(match values.len() {
+        0 => quote!(alipay_params::AlipayValue::Null),
+        1 => {
+            let value = &values[0];
+            quote!(alipay_params::AlipayParams::to_alipay_value(#value))
+        }
+        _ => quote! {
+            alipay_params::AlipayValue::Array(vec![
+                #(alipay_params::AlipayParams::to_alipay_value(#values)),*
+            ])
+        },
+    })
+}
+
+fn enum_body(data: &DataEnum, container_attr: &ContainerAttr) -> Result<TokenStream2> {
+    let tag = container_attr.tag.as_deref();
+    let mut arms: Vec<TokenStream2> = Vec::new();
+    for variant in data.variants.iter() {
+        let variant_attr = VariantAttr::from_attrs(&variant.attrs)?;
+        let ident = &variant.ident;
+        let name = match (variant_attr.rename, container_attr.rename_all) {
+            (Some(rename), _) => rename,
+            (None, Some(rule)) => rule.apply_to_variant(&ident.unraw().to_string()),
+            (None, None) => ident.unraw().to_string(),
+        };
+
+        let arm = match &variant.fields {
+            Fields::Unit => {
+                let value = match tag {
+                    Some(tag) => quote! {
+                        alipay_params::AlipayValue::from(alipay_params::IndexMap::from([(
+                            #tag.to_string(),
+                            alipay_params::AlipayValue::String(#name.to_string()),
+                        )]))
+                    },
+                    None => quote!(alipay_params::AlipayValue::String(#name.to_string())),
+                };
+                quote!(Self::#ident => { #value })
+            }
+            Fields::Named(fields) => {
+                let bindings: Vec<_> = (0..fields.named.len())
+                    .map(|i| format_ident!("__field{}", i))
+                    .collect();
+                let idents = fields.named.iter().map(|field| &field.ident);
+                let values = bindings.iter().map(|binding| quote!(#binding)).collect();
+                let value = match tag {
+                    Some(tag) => named_fields_body(fields, values, None, Some((tag, &name)))?,
+                    None => {
+                        let inner = named_fields_body(fields, values, None, None)?;
+                        externally_tagged(&name, inner)
+                    }
+                };
+                quote!(Self::#ident { #(#idents: #bindings),* } => { #value })
+            }
+            Fields::Unnamed(fields) => {
+                let bindings: Vec<_> = (0..fields.unnamed.len())
+                    .map(|i| format_ident!("__field{}", i))
+                    .collect();
+                let values = bindings.iter().map(|binding| quote!(#binding)).collect();
+                let inner = unnamed_fields_body(fields, values)?;
+                let value = match tag {
+                    // 成员的内容和标签放在同一个对象中，字段的类型在validate中检查
+                    Some(tag) if fields.unnamed.len() == 1 => quote! {
+                        let mut result = alipay_params::__private::flatten(__field0);
+                        result.insert(#tag.to_string(), alipay_params::AlipayValue::String(#name.to_string()));
+                        alipay_params::AlipayValue::from(result)
+                    },
+                    Some(_) => {
+                        return Err(Error::new_spanned(
+                            variant,
+                            "internally tagged enums do not support tuple variants",
+                        ))
+                    }
+                    None => externally_tagged(&name, inner),
+                };
+                quote!(Self::#ident(#(#bindings),*) => { #value })
+            }
+        };
+        arms.push(arm);
+    }
     Ok(quote! {
-        impl #impl_generics alipay_params::AlipayParams for #struct_name #ty_generics #where_clause {
-            fn to_alipay_value(self) -> alipay_params::AlipayValue {
-                let mut result: std::collections::HashMap<String, alipay_params::AlipayValue> = std::collections::HashMap::new();
-                #(#to_field_value_token_streams)*
-                alipay_params::AlipayValue::from(result)
+        match self {
+            #(#arms)*
+        }
+    })
+}
+
+fn enum_validate(data: &DataEnum, container_attr: &ContainerAttr) -> Result<TokenStream2> {
+    if data.variants.is_empty() {
+        return Ok(TokenStream2::new());
+    }
+    let mut arms: Vec<TokenStream2> = Vec::new();
+    for variant in data.variants.iter() {
+        let ident = &variant.ident;
+        let arm = match &variant.fields {
+            Fields::Unit => quote!(Self::#ident => {}),
+            Fields::Named(fields) => {
+                let bindings: Vec<_> = (0..fields.named.len())
+                    .map(|i| format_ident!("__field{}", i))
+                    .collect();
+                let idents = fields.named.iter().map(|field| &field.ident);
+                let values = bindings.iter().map(|binding| quote!(#binding)).collect();
+                let checks = named_fields_validate(fields, values, None)?;
+                quote! {
+                    #[allow(unused_variables)]
+                    Self::#ident { #(#idents: #bindings),* } => { #checks }
+                }
             }
+            Fields::Unnamed(fields) => {
+                let bindings: Vec<_> = (0..fields.unnamed.len())
+                    .map(|i| format_ident!("__field{}", i))
+                    .collect();
+                let values = bindings.iter().map(|binding| quote!(#binding)).collect();
+                let mut checks = unnamed_fields_validate(values);
+                if container_attr.tag.is_some() && fields.unnamed.len() == 1 {
+                    // 带标签的成员中的值需要是对象，编译错误指向字段的类型
+                    let ty = &fields.unnamed[0].ty;
+                    checks.extend(quote_spanned!(ty.span()=> alipay_params::__private::assert_object::<#ty>();));
+                }
+                quote!(Self::#ident(#(#bindings),*) => { #checks })
+            }
+        };
+        arms.push(arm);
+    }
+    Ok(quote! {
+        match self {
+            #(#arms)*
         }
-    }.into())
+    })
+}
+
+/// 转换为`{"成员名": 值}`
+fn externally_tagged(name: &str, inner: TokenStream2) -> TokenStream2 {
+    quote! {
+        let value = { #inner };
+        alipay_params::AlipayValue::from(alipay_params::IndexMap::from([(#name.to_string(), value)]))
+    }
 }
@@ -1,7 +1,36 @@
+mod money;
+mod object;
+mod params_ref;
+mod ser;
+mod validate;
+
 pub use alipay_macros::*;
+pub use indexmap::IndexMap;
+pub use money::{Money, MoneyError};
+pub use object::AlipayObject;
+pub use params_ref::AlipayParamsRef;
+pub use validate::{ValidateField, ValidationError, ValidationRule};
+
+/// derive宏生成的代码使用，不属于公开的api
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::object::{assert_object, flatten};
+    pub use regex::Regex;
+}
+use serde::Serialize;
 use serde_json::{Map, Number, Value};
-use std::collections::HashMap;
-
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::Index,
+    rc::Rc,
+};
+
+/// 参数值
+///
+/// Object会保留插入的顺序，derive生成的对象按照字段声明的顺序输出，
+/// 所以相同的参数总是生成相同的biz_content。
+#[derive(Debug, Clone, PartialEq)]
 pub enum AlipayValue {
     Null,
     Bool(bool),
@@ -10,7 +39,7 @@ pub enum AlipayValue {
     Tuple((String, Value)),
     TupleArray(Vec<(String, Value)>),
     Array(Vec<AlipayValue>),
-    Object(HashMap<String, AlipayValue>),
+    Object(IndexMap<String, AlipayValue>),
 }
 
 impl From<bool> for AlipayValue {
@@ -97,9 +126,19 @@ impl From<Vec<AlipayValue>> for AlipayValue {
     }
 }
 
+impl<T: Into<AlipayValue>> From<IndexMap<String, T>> for AlipayValue {
+    fn from(value: IndexMap<String, T>) -> Self {
+        let mut data: IndexMap<String, AlipayValue> = IndexMap::new();
+        for (key, val) in value {
+            data.insert(key, val.into());
+        }
+        AlipayValue::Object(data)
+    }
+}
+
 impl<T: Into<AlipayValue>> From<HashMap<String, T>> for AlipayValue {
     fn from(value: HashMap<String, T>) -> Self {
-        let mut data: HashMap<String, AlipayValue> = HashMap::new();
+        let mut data: IndexMap<String, AlipayValue> = IndexMap::new();
         for (key, val) in value {
             data.insert(key, val.into());
         }
@@ -109,7 +148,7 @@ impl<T: Into<AlipayValue>> From<HashMap<String, T>> for AlipayValue {
 
 impl<'a, T: Into<AlipayValue>> From<HashMap<&'a str, T>> for AlipayValue {
     fn from(value: HashMap<&'a str, T>) -> Self {
-        let mut data: HashMap<String, AlipayValue> = HashMap::new();
+        let mut data: IndexMap<String, AlipayValue> = IndexMap::new();
         for (key, val) in value {
             data.insert(key.to_string(), val.into());
         }
@@ -117,7 +156,7 @@ impl<'a, T: Into<AlipayValue>> From<HashMap<&'a str, T>> for AlipayValue {
     }
 }
 
-fn json_value_to_alipay_value(val: Value) -> AlipayValue {
+pub(crate) fn json_value_to_alipay_value(val: Value) -> AlipayValue {
     match val {
         Value::Null => AlipayValue::Null,
         Value::Bool(v) => AlipayValue::Bool(v),
@@ -131,7 +170,7 @@ fn json_value_to_alipay_value(val: Value) -> AlipayValue {
             AlipayValue::Array(array)
         }
         Value::Object(v) => {
-            let mut map = HashMap::new();
+            let mut map = IndexMap::new();
             for (key, val) in v {
                 map.insert(key, json_value_to_alipay_value(val));
             }
@@ -165,6 +204,72 @@ impl AlipayValue {
     pub fn is_object(&self) -> bool {
         matches!(self, AlipayValue::Object(_))
     }
+    /// 获取对象中key对应的值，不是Object或者key不存在时返回None
+    ///
+    /// Tuple和TupleArray中保存的是json值，需要先通过into_object转换。
+    pub fn get(&self, key: &str) -> Option<&AlipayValue> {
+        match self {
+            AlipayValue::Object(v) => v.get(key),
+            _ => None,
+        }
+    }
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            AlipayValue::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            AlipayValue::Number(v) => v.as_i64(),
+            _ => None,
+        }
+    }
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            AlipayValue::Number(v) => v.as_u64(),
+            _ => None,
+        }
+    }
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            AlipayValue::Number(v) => v.as_f64(),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            AlipayValue::String(v) => Some(v),
+            _ => None,
+        }
+    }
+    pub fn as_array(&self) -> Option<&Vec<AlipayValue>> {
+        match self {
+            AlipayValue::Array(v) => Some(v),
+            _ => None,
+        }
+    }
+    pub fn as_object(&self) -> Option<&IndexMap<String, AlipayValue>> {
+        match self {
+            AlipayValue::Object(v) => Some(v),
+            _ => None,
+        }
+    }
+    /// 转换成键值对，Tuple和TupleArray也会被转换，其他类型返回None
+    pub fn into_object(self) -> Option<IndexMap<String, AlipayValue>> {
+        match self {
+            AlipayValue::Tuple((key, val)) => {
+                Some(IndexMap::from([(key, json_value_to_alipay_value(val))]))
+            }
+            AlipayValue::TupleArray(v) => Some(
+                v.into_iter()
+                    .map(|(key, val)| (key, json_value_to_alipay_value(val)))
+                    .collect(),
+            ),
+            AlipayValue::Object(v) => Some(v),
+            _ => None,
+        }
+    }
     pub fn to_json_value(self) -> Value {
         match self {
             AlipayValue::Null => Value::Null,
@@ -201,8 +306,32 @@ impl AlipayValue {
     }
 }
 
+static NULL: AlipayValue = AlipayValue::Null;
+
+/// `value["key"]`，不是Object或者key不存在时返回Null
+impl Index<&str> for AlipayValue {
+    type Output = AlipayValue;
+
+    fn index(&self, key: &str) -> &AlipayValue {
+        self.get(key).unwrap_or(&NULL)
+    }
+}
+
+/// `value[0]`，不是Array或者越界时返回Null
+impl Index<usize> for AlipayValue {
+    type Output = AlipayValue;
+
+    fn index(&self, index: usize) -> &AlipayValue {
+        self.as_array().and_then(|v| v.get(index)).unwrap_or(&NULL)
+    }
+}
+
 pub trait AlipayParams {
     fn to_alipay_value(self) -> AlipayValue;
+    /// 签名前校验参数，derive宏会根据`#[alipay(max_len, range, pattern, required)]`生成
+    fn validate(&self) -> Result<(), ValidationError> {
+        Ok(())
+    }
 }
 
 macro_rules! alipay_params_implement_number {
@@ -221,9 +350,11 @@ alipay_params_implement_number!(u8, u16, u32, u64);
 alipay_params_implement_number!(i8, i16, i32, i64);
 alipay_params_implement_number!(usize, isize);
 
+/// 金额请使用Money，避免浮点数的精度问题
 impl AlipayParams for f32 {
     fn to_alipay_value(self) -> AlipayValue {
-        let data = Number::from_f64(self as f64);
+        // 直接转换为f64会把0.1变成0.10000000149011612
+        let data = self.to_string().parse().ok().and_then(Number::from_f64);
         if let Some(val) = data {
             AlipayValue::Number(val)
         } else {
@@ -255,9 +386,53 @@ impl AlipayParams for String {
     }
 }
 
-impl<'a> AlipayParams for &'a str {
+impl AlipayParams for char {
+    fn to_alipay_value(self) -> AlipayValue {
+        AlipayValue::String(self.to_string())
+    }
+}
+
+impl<T> AlipayParams for Cow<'_, T>
+where
+    T: ToOwned + ?Sized,
+    T::Owned: AlipayParams,
+{
+    fn to_alipay_value(self) -> AlipayValue {
+        self.into_owned().to_alipay_value()
+    }
+    /// 借用的值需要先转换为Owned才能校验
+    fn validate(&self) -> Result<(), ValidationError> {
+        match self {
+            Cow::Borrowed(v) => (*v).to_owned().validate(),
+            Cow::Owned(v) => v.validate(),
+        }
+    }
+}
+
+impl<T> AlipayParams for Box<T>
+where
+    T: AlipayParams,
+{
+    fn to_alipay_value(self) -> AlipayValue {
+        (*self).to_alipay_value()
+    }
+    fn validate(&self) -> Result<(), ValidationError> {
+        (**self).validate()
+    }
+}
+
+/// 有其他引用时会clone内部的值
+impl<T> AlipayParams for Rc<T>
+where
+    T: AlipayParams + Clone,
+{
     fn to_alipay_value(self) -> AlipayValue {
-        AlipayValue::String(self.to_owned())
+        Rc::try_unwrap(self)
+            .unwrap_or_else(|rc| (*rc).clone())
+            .to_alipay_value()
+    }
+    fn validate(&self) -> Result<(), ValidationError> {
+        (**self).validate()
     }
 }
 
@@ -272,6 +447,12 @@ where
             AlipayValue::Null
         }
     }
+    fn validate(&self) -> Result<(), ValidationError> {
+        match self {
+            Some(v) => v.validate(),
+            None => Ok(()),
+        }
+    }
 }
 
 impl<T1, T2> AlipayParams for (T1, T2)
@@ -282,62 +463,73 @@ where
     fn to_alipay_value(self) -> AlipayValue {
         AlipayValue::Tuple((self.0.into(), self.1.to_alipay_value().to_json_value()))
     }
+    fn validate(&self) -> Result<(), ValidationError> {
+        self.1.validate()
+    }
 }
 
 impl<T, const N: usize> AlipayParams for [T; N]
 where
-    T: AlipayParams + Clone,
+    T: AlipayParams,
 {
     fn to_alipay_value(self) -> AlipayValue {
-        if !self.is_empty() {
-            let mut i = 0;
-            let len = self.len();
-            let temp = self[i].clone().to_alipay_value();
-            if temp.is_tuple() {
-                i += 1;
-                let mut array = Vec::new();
-                if let AlipayValue::Tuple((key, val)) = temp {
-                    array.push((key, val));
-                }
-                while len > i {
-                    let temp = self[i].clone().to_alipay_value();
-                    if temp.is_tuple() {
-                        if let AlipayValue::Tuple((key, val)) = temp {
-                            array.push((key, val));
-                        }
-                    }
-                    i += 1;
-                }
-                AlipayValue::TupleArray(array)
-            } else {
-                i += 1;
-                let mut array = Vec::new();
-                array.push(temp);
-                while len > i {
-                    let temp = self[i].clone().to_alipay_value();
-                    array.push(temp);
-                    i += 1;
-                }
-                AlipayValue::Array(array)
-            }
-        } else {
-            AlipayValue::Null
+        collection_to_alipay_value(self)
+    }
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_collection(self)
+    }
+}
+
+impl<T1, T2> AlipayParams for IndexMap<T1, T2>
+where
+    T1: Into<String>,
+    T2: AlipayParams,
+{
+    fn to_alipay_value(self) -> AlipayValue {
+        let mut map = IndexMap::new();
+        for (key, val) in self {
+            map.insert(key.into(), val.to_alipay_value());
         }
+        AlipayValue::Object(map)
+    }
+    fn validate(&self) -> Result<(), ValidationError> {
+        self.values().try_for_each(AlipayParams::validate)
     }
 }
 
+/// HashMap本身是无序的，需要固定顺序时请使用IndexMap
 impl<T1, T2> AlipayParams for HashMap<T1, T2>
 where
     T1: Into<String>,
     T2: AlipayParams,
 {
     fn to_alipay_value(self) -> AlipayValue {
-        let mut map = HashMap::new();
+        let mut map = IndexMap::new();
+        for (key, val) in self {
+            map.insert(key.into(), val.to_alipay_value());
+        }
+        AlipayValue::Object(map)
+    }
+    fn validate(&self) -> Result<(), ValidationError> {
+        self.values().try_for_each(AlipayParams::validate)
+    }
+}
+
+impl<T1, T2> AlipayParams for BTreeMap<T1, T2>
+where
+    T1: Into<String>,
+    T2: AlipayParams,
+{
+    fn to_alipay_value(self) -> AlipayValue {
+        let mut map = IndexMap::new();
         for (key, val) in self {
             map.insert(key.into(), val.to_alipay_value());
         }
         AlipayValue::Object(map)
     }
+    fn validate(&self) -> Result<(), ValidationError> {
+        self.values().try_for_each(AlipayParams::validate)
+    }
 }
 
 impl AlipayParams for Value {
@@ -350,41 +542,67 @@ impl<T> AlipayParams for Vec<T>
 where
     T: AlipayParams,
 {
-    fn to_alipay_value(mut self) -> AlipayValue {
-        if !self.is_empty() {
-            let mut i = 0;
-            let len = self.len();
-            let temp = self.remove(0).to_alipay_value();
-            if temp.is_tuple() {
-                i += 1;
-                let mut array = Vec::new();
-                if let AlipayValue::Tuple((key, val)) = temp {
-                    array.push((key, val));
-                }
-                while len > i {
-                    let temp = self.remove(0).to_alipay_value();
-                    if temp.is_tuple() {
-                        if let AlipayValue::Tuple((key, val)) = temp {
-                            array.push((key, val));
-                        }
+    fn to_alipay_value(self) -> AlipayValue {
+        collection_to_alipay_value(self)
+    }
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_collection(self)
+    }
+}
+
+/// HashSet本身是无序的，需要固定顺序时请使用Vec
+impl<T> AlipayParams for HashSet<T>
+where
+    T: AlipayParams,
+{
+    fn to_alipay_value(self) -> AlipayValue {
+        collection_to_alipay_value(self)
+    }
+    fn validate(&self) -> Result<(), ValidationError> {
+        self.iter().try_for_each(AlipayParams::validate)
+    }
+}
+
+/// 依次校验集合中的元素，字段名加上元素的下标
+pub(crate) fn validate_collection<T: AlipayParams>(values: &[T]) -> Result<(), ValidationError> {
+    for (i, value) in values.iter().enumerate() {
+        value.validate().map_err(|e| e.nested(&i.to_string()))?;
+    }
+    Ok(())
+}
+
+/// 集合转换规则：
+/// - 所有元素都是Tuple时转换为TupleArray，也就是一个对象
+/// - 其他情况转换为Array，其中的Tuple转换为只有一个键值对的对象
+/// - 空集合转换为空数组
+pub(crate) fn collection_to_alipay_value<I>(iter: I) -> AlipayValue
+where
+    I: IntoIterator,
+    I::Item: AlipayParams,
+{
+    let values: Vec<AlipayValue> = iter.into_iter().map(|v| v.to_alipay_value()).collect();
+    if !values.is_empty() && values.iter().all(|v| v.is_tuple()) {
+        AlipayValue::TupleArray(
+            values
+                .into_iter()
+                .filter_map(|v| match v {
+                    AlipayValue::Tuple(tuple) => Some(tuple),
+                    _ => None,
+                })
+                .collect(),
+        )
+    } else {
+        AlipayValue::Array(
+            values
+                .into_iter()
+                .map(|v| match v {
+                    AlipayValue::Tuple(_) => {
+                        AlipayValue::Object(v.into_object().unwrap_or_default())
                     }
-                    i += 1;
-                }
-                AlipayValue::TupleArray(array)
-            } else {
-                i += 1;
-                let mut array = Vec::new();
-                array.push(temp);
-                while len > i {
-                    let temp = self.remove(0).to_alipay_value();
-                    array.push(temp);
-                    i += 1;
-                }
-                AlipayValue::Array(array)
-            }
-        } else {
-            AlipayValue::Null
-        }
+                    v => v,
+                })
+                .collect(),
+        )
     }
 }
 
@@ -399,3 +617,236 @@ impl AlipayParams for AlipayValue {
         self
     }
 }
+
+/// 把实现了serde::Serialize的类型作为参数
+///
+/// ```rust
+/// use alipay_params::{AlipayParams, SerdeParams};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct QueryParam {
+///     operation: String,
+///     page_num: i32,
+/// }
+///
+/// let query = QueryParam {
+///     operation: "ITEM_PAGEQUERY".to_owned(),
+///     page_num: 1,
+/// };
+/// let value = SerdeParams(query).to_alipay_value();
+/// assert!(value.is_object());
+/// ```
+///
+/// 序列化可能失败（比如map的key不是字符串），validate会返回serde的错误信息，
+/// client在签名前会调用validate。没有校验就调用to_alipay_value时会panic，
+/// 可以使用try_to_alipay_value获取错误。
+///
+/// ```rust
+/// use alipay_params::{AlipayParams, SerdeParams, ValidationRule};
+/// use std::collections::HashMap;
+///
+/// let params = SerdeParams(HashMap::from([((1, 2), "a")]));
+/// let err = params.validate().unwrap_err();
+/// assert!(matches!(err.rule, ValidationRule::Serialize(_)));
+/// assert!(params.try_to_alipay_value().is_err());
+/// ```
+pub struct SerdeParams<T>(pub T);
+
+impl<T> SerdeParams<T>
+where
+    T: Serialize,
+{
+    /// 转换为参数值，序列化失败时返回serde的错误
+    pub fn try_to_alipay_value(&self) -> Result<AlipayValue, serde_json::Error> {
+        serde_json::to_value(&self.0).map(json_value_to_alipay_value)
+    }
+}
+
+impl<T> AlipayParams for SerdeParams<T>
+where
+    T: Serialize,
+{
+    fn to_alipay_value(self) -> AlipayValue {
+        match self.try_to_alipay_value() {
+            Ok(value) => value,
+            Err(err) => panic!("SerdeParams cannot be serialized: {}", err),
+        }
+    }
+    fn validate(&self) -> Result<(), ValidationError> {
+        match self.try_to_alipay_value() {
+            Ok(_) => Ok(()),
+            Err(err) => Err(ValidationError::new(
+                "",
+                ValidationRule::Serialize(err.to_string()),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serde_params_reports_errors() {
+        let params = SerdeParams(HashMap::from([((1, 2), "a")]));
+        let err = params.validate().unwrap_err();
+        assert_eq!(err.field, "");
+        assert!(matches!(err.rule, ValidationRule::Serialize(_)));
+        assert!(err.to_string().starts_with("params cannot be serialized"));
+        let err = err.nested("extend_params");
+        assert_eq!(err.field, "extend_params");
+        assert!(err
+            .to_string()
+            .starts_with("extend_params cannot be serialized"));
+    }
+
+    #[test]
+    #[should_panic(expected = "SerdeParams cannot be serialized")]
+    fn serde_params_panics_without_validate() {
+        SerdeParams(HashMap::from([((1, 2), "a")])).to_alipay_value();
+    }
+
+    #[test]
+    fn serde_params_converts_values() {
+        let params = SerdeParams(BTreeMap::from([("a", 1), ("b", 2)]));
+        assert!(params.validate().is_ok());
+        assert_eq!(params.to_alipay_value_ref()["b"].as_i64(), Some(2));
+        assert_eq!(params.to_alipay_value()["a"].as_i64(), Some(1));
+    }
+
+    #[test]
+    fn collections_of_tuples_become_objects() {
+        let value = vec![("image_type", "png"), ("image_name", "test")].to_alipay_value();
+        assert!(value.is_tuple_array());
+        assert_eq!(
+            value.to_json_value(),
+            json!({"image_type": "png", "image_name": "test"})
+        );
+        let value = [("a", 1)].to_alipay_value();
+        assert_eq!(value.to_json_value(), json!({"a": 1}));
+    }
+
+    #[test]
+    fn collections_keep_every_element() {
+        let value = vec![("a", 1).to_alipay_value(), AlipayValue::from(2)].to_alipay_value();
+        assert_eq!(value.to_json_value(), json!([{"a": 1}, 2]));
+        let value = vec![Some(1), None].to_alipay_value();
+        assert_eq!(value.to_json_value(), json!([1, null]));
+        assert_eq!(
+            Vec::<u32>::new().to_alipay_value(),
+            AlipayValue::Array(Vec::new())
+        );
+        assert_eq!(
+            HashSet::from([1]).to_alipay_value().to_json_value(),
+            json!([1])
+        );
+    }
+
+    #[test]
+    fn maps_become_objects() {
+        let value = BTreeMap::from([("b", 2), ("a", 1)]).to_alipay_value();
+        assert_eq!(value.to_json_value(), json!({"a": 1, "b": 2}));
+        let value = IndexMap::from([("b", vec![1]), ("a", vec![])]).to_alipay_value();
+        assert_eq!(
+            value.as_object().unwrap().keys().collect::<Vec<_>>(),
+            ["b", "a"]
+        );
+        let value = HashMap::from([("a", None::<u32>)]).to_alipay_value();
+        assert_eq!(value["a"], AlipayValue::Null);
+    }
+
+    #[test]
+    fn options_and_wrappers() {
+        assert_eq!(None::<String>.to_alipay_value(), AlipayValue::Null);
+        assert_eq!(
+            Some("a".to_owned()).to_alipay_value(),
+            AlipayValue::from("a")
+        );
+        assert_eq!(Box::new(1u8).to_alipay_value(), AlipayValue::from(1u8));
+        assert_eq!(Rc::new(true).to_alipay_value(), AlipayValue::Bool(true));
+        assert_eq!(
+            Cow::<str>::Borrowed("a").to_alipay_value(),
+            AlipayValue::from("a")
+        );
+        assert_eq!('x'.to_alipay_value(), AlipayValue::from("x"));
+        assert_eq!(0.1f32.to_alipay_value().as_f64(), Some(0.1));
+        assert_eq!(f64::NAN.to_alipay_value(), AlipayValue::Null);
+        assert_eq!(().to_alipay_value(), AlipayValue::Null);
+    }
+
+    #[test]
+    fn collection_validation_reports_index() {
+        let values = vec![None, Some(SerdeParams(BTreeMap::from([((1, 2), 1)])))];
+        let err = values.validate().unwrap_err();
+        assert_eq!(err.field, "1");
+        assert_eq!(None::<SerdeParams<()>>.validate(), Ok(()));
+    }
+
+    fn unserializable() -> SerdeParams<HashMap<(u8, u8), u8>> {
+        // json对象的key只能是字符串
+        SerdeParams(HashMap::from([((1, 2), 3)]))
+    }
+
+    fn is_range_error<T: AlipayParams>(params: T) -> bool {
+        matches!(
+            params.validate().map_err(|e| e.rule),
+            Err(ValidationRule::Range { .. })
+        )
+    }
+
+    fn is_serialize_error<T: AlipayParams>(params: T) -> bool {
+        matches!(
+            params.validate().map_err(|e| e.rule),
+            Err(ValidationRule::Serialize(_))
+        )
+    }
+
+    #[test]
+    fn cow_and_rc_validate_inner_values() {
+        let zero = Money::ZERO;
+        assert!(is_range_error(Cow::Borrowed(&zero)));
+        assert!(is_range_error(Cow::<Money>::Owned(zero)));
+        assert!(is_range_error(Cow::Borrowed(&[Money::MIN, zero][..])));
+        assert!(Cow::Borrowed(&Money::MIN).validate().is_ok());
+
+        assert!(is_range_error(Rc::new(zero)));
+        assert!(is_range_error(Rc::new(vec![Money::MIN, zero])));
+        assert!(Rc::new(Money::MIN).validate().is_ok());
+    }
+
+    #[test]
+    fn tuples_validate_values() {
+        assert!(is_range_error(("total_amount", Money::ZERO)));
+        assert!(is_serialize_error(("extend_params", unserializable())));
+        assert!(("total_amount", Money::MIN).validate().is_ok());
+    }
+
+    #[test]
+    fn maps_validate_values() {
+        assert!(is_range_error(IndexMap::from([
+            ("a", Money::MIN),
+            ("b", Money::ZERO)
+        ])));
+        assert!(is_range_error(HashMap::from([("a", Money::ZERO)])));
+        assert!(is_range_error(BTreeMap::from([("a", Money::ZERO)])));
+        assert!(is_serialize_error(IndexMap::from([(
+            "a",
+            unserializable()
+        )])));
+        assert!(is_serialize_error(HashMap::from([("a", unserializable())])));
+        assert!(is_serialize_error(BTreeMap::from([(
+            "a",
+            unserializable()
+        )])));
+        assert!(BTreeMap::from([("a", Money::MIN)]).validate().is_ok());
+    }
+
+    #[test]
+    fn sets_validate_elements() {
+        assert!(is_range_error(HashSet::from([Money::MIN, Money::ZERO])));
+        assert!(HashSet::from([Money::MIN, Money::MAX]).validate().is_ok());
+    }
+}
@@ -0,0 +1,265 @@
+use crate::{AlipayParams, AlipayValue, ValidationError, ValidationRule};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::{error::Error, fmt, str::FromStr};
+
+/// 金额，单位为元，精确到小数点后两位
+///
+/// 内部以分为单位保存，避免浮点数的精度问题，用于total_amount、refund_amount、trans_amount等参数。
+/// 金额可以为0，比如响应中的退款金额；作为请求参数时validate会检查金额在[0.01, 100000000.00]之间。
+///
+/// ```rust
+/// use alipay_params::{AlipayParams, Money};
+///
+/// let total: Money = "88.88".parse().unwrap();
+/// assert_eq!(total.to_string(), "88.88");
+/// assert_eq!(total.to_alipay_value().as_str(), Some("88.88"));
+///
+/// // 分摊金额，除不尽的部分从第一份开始每份多分一分钱
+/// let parts = total.split(3).unwrap();
+/// assert_eq!(parts[0].to_string(), "29.63");
+/// assert_eq!(parts[2].to_string(), "29.62");
+///
+/// let refund = Money::from_fen(1000).unwrap();
+/// assert_eq!(total.checked_sub(refund).unwrap().to_string(), "78.88");
+/// assert!("0.001".parse::<Money>().is_err());
+///
+/// let zero: Money = "0.00".parse().unwrap();
+/// assert_eq!(zero, Money::ZERO);
+/// assert!(zero.validate().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(u64);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoneyError {
+    /// 格式错误，比如超过两位小数
+    Invalid(String),
+    /// 超过Money::MAX
+    OutOfRange(String),
+}
+
+impl fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoneyError::Invalid(amount) => write!(f, "invalid amount: {}", amount),
+            MoneyError::OutOfRange(amount) => write!(f, "amount out of range: {}", amount),
+        }
+    }
+}
+
+impl Error for MoneyError {}
+
+impl Money {
+    /// 0元，可以出现在响应中，不能作为请求的金额
+    pub const ZERO: Money = Money(0);
+    /// 请求中允许的最小金额0.01元
+    pub const MIN: Money = Money(1);
+    /// 最大金额100000000.00元
+    pub const MAX: Money = Money(10_000_000_000);
+
+    /// 通过分创建，超过Money::MAX时返回错误
+    pub fn from_fen(fen: u64) -> Result<Self, MoneyError> {
+        let money = Money(fen);
+        if money > Money::MAX {
+            return Err(MoneyError::OutOfRange(money.to_string()));
+        }
+        Ok(money)
+    }
+    pub fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+    /// 以分为单位的金额
+    pub fn fen(&self) -> u64 {
+        self.0
+    }
+    pub fn checked_add(self, rhs: Money) -> Option<Money> {
+        Money::from_fen(self.0.checked_add(rhs.0)?).ok()
+    }
+    /// 结果小于0时返回None
+    pub fn checked_sub(self, rhs: Money) -> Option<Money> {
+        Money::from_fen(self.0.checked_sub(rhs.0)?).ok()
+    }
+    pub fn checked_mul(self, rhs: u64) -> Option<Money> {
+        Money::from_fen(self.0.checked_mul(rhs)?).ok()
+    }
+    /// 平均分成parts份，除不尽的部分从第一份开始每份多分一分钱
+    ///
+    /// parts为0或者每份不足0.01元时返回None。
+    pub fn split(self, parts: u64) -> Option<Vec<Money>> {
+        if parts == 0 || self.0 < parts {
+            return None;
+        }
+        let base = self.0 / parts;
+        let rem = self.0 % parts;
+        Some(
+            (0..parts)
+                .map(|i| Money(if i < rem { base + 1 } else { base }))
+                .collect(),
+        )
+    }
+    /// 按比例分配，用于分账等场景，除不尽的部分从第一份开始每份多分一分钱
+    ///
+    /// 比例之和为0或者有一份不足0.01元时返回None。
+    pub fn allocate(self, ratios: &[u64]) -> Option<Vec<Money>> {
+        let total: u64 = ratios.iter().try_fold(0u64, |sum, r| sum.checked_add(*r))?;
+        if total == 0 {
+            return None;
+        }
+        let mut parts = ratios
+            .iter()
+            .map(|r| (self.0 as u128 * *r as u128 / total as u128) as u64)
+            .collect::<Vec<u64>>();
+        let rem = self.0 - parts.iter().sum::<u64>();
+        for part in parts.iter_mut().take(rem as usize) {
+            *part += 1;
+        }
+        if parts.contains(&0) {
+            return None;
+        }
+        Some(parts.into_iter().map(Money).collect())
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:02}", self.0 / 100, self.0 % 100)
+    }
+}
+
+impl FromStr for Money {
+    type Err = MoneyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || MoneyError::Invalid(s.to_owned());
+        let (yuan, fen) = match s.split_once('.') {
+            Some((yuan, fen)) => (yuan, fen),
+            None => (s, ""),
+        };
+        if yuan.is_empty()
+            || fen.len() > 2
+            || !yuan.bytes().all(|b| b.is_ascii_digit())
+            || !fen.bytes().all(|b| b.is_ascii_digit())
+            || (s.contains('.') && fen.is_empty())
+        {
+            return Err(invalid());
+        }
+        let yuan = yuan
+            .parse::<u64>()
+            .map_err(|_| MoneyError::OutOfRange(s.to_owned()))?;
+        let fen = format!("{:0<2}", fen)
+            .parse::<u64>()
+            .map_err(|_| invalid())?;
+        yuan.checked_mul(100)
+            .and_then(|v| v.checked_add(fen))
+            .ok_or_else(|| MoneyError::OutOfRange(s.to_owned()))
+            .and_then(Money::from_fen)
+    }
+}
+
+/// 转换为两位小数的字符串，比如"0.10"
+impl AlipayParams for Money {
+    fn to_alipay_value(self) -> AlipayValue {
+        AlipayValue::String(self.to_string())
+    }
+    /// 请求中的金额需要在[0.01, 100000000.00]之间
+    fn validate(&self) -> Result<(), ValidationError> {
+        if *self < Money::MIN || *self > Money::MAX {
+            return Err(ValidationError::new(
+                "",
+                ValidationRule::Range {
+                    min: Some(0.01),
+                    max: Some(100_000_000.0),
+                },
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// 支持字符串和数字，响应中的金额一般是字符串
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Value::deserialize(deserializer)? {
+            Value::String(s) => s.parse().map_err(de::Error::custom),
+            Value::Number(n) => n.to_string().parse().map_err(de::Error::custom),
+            v => Err(de::Error::custom(format!("invalid amount: {}", v))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!("88.88".parse(), Ok(Money(8888)));
+        assert_eq!("1".parse(), Ok(Money(100)));
+        assert_eq!("0.1".parse(), Ok(Money(10)));
+        assert_eq!("0.00".parse(), Ok(Money::ZERO));
+        assert_eq!("100000000.00".parse(), Ok(Money::MAX));
+        for invalid in ["", ".1", "1.", "0.001", "-1", "1e2", " 1", "1.2.3"] {
+            assert_eq!(
+                invalid.parse::<Money>(),
+                Err(MoneyError::Invalid(invalid.to_owned())),
+                "{invalid}"
+            );
+        }
+        assert!(matches!(
+            "100000000.01".parse::<Money>(),
+            Err(MoneyError::OutOfRange(_))
+        ));
+        assert!(matches!(
+            "99999999999999999999".parse::<Money>(),
+            Err(MoneyError::OutOfRange(_))
+        ));
+    }
+
+    #[test]
+    fn bounds_are_checked_on_validate() {
+        assert!(Money::ZERO.validate().is_err());
+        assert!(Money::MIN.validate().is_ok());
+        assert!(Money::MAX.validate().is_ok());
+        let err = Money::ZERO.validate().unwrap_err().nested("total_amount");
+        assert_eq!(err.field, "total_amount");
+        assert_eq!(
+            err.to_string(),
+            "total_amount is out of range [0.01, 100000000]"
+        );
+    }
+
+    #[test]
+    fn deserialize_accepts_zero() {
+        let zero: Money = serde_json::from_str(r#""0.00""#).unwrap();
+        assert!(zero.is_zero());
+        let amount: Money = serde_json::from_str("12.5").unwrap();
+        assert_eq!(amount, Money(1250));
+        assert!(serde_json::from_str::<Money>(r#""abc""#).is_err());
+        assert!(serde_json::from_str::<Money>("null").is_err());
+        assert_eq!(serde_json::to_string(&Money(5)).unwrap(), r#""0.05""#);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let ten = Money(1000);
+        assert_eq!(ten.checked_sub(ten), Some(Money::ZERO));
+        assert_eq!(Money::ZERO.checked_sub(Money::MIN), None);
+        assert_eq!(Money::MAX.checked_add(Money::MIN), None);
+        assert_eq!(ten.checked_mul(3), Some(Money(3000)));
+        assert_eq!(Money(2).split(3), None);
+        assert_eq!(Money(10).split(0), None);
+        assert_eq!(
+            Money(1000).allocate(&[1, 2]),
+            Some(vec![Money(334), Money(666)])
+        );
+        assert_eq!(Money(1).allocate(&[1, 1]), None);
+        assert_eq!(Money(1).allocate(&[0, 0]), None);
+    }
+}
@@ -0,0 +1,52 @@
+use crate::{AlipayParams, AlipayValue, IndexMap};
+use std::{
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
+};
+
+/// 总是转换为对象（或者Null）的参数，只有这些类型可以使用`#[alipay(flatten)]`展开，
+/// 或者作为带标签枚举中tuple成员的值
+///
+/// derive宏会为具名字段的结构体实现，map和键值对也实现了这个trait。
+/// AlipayValue、serde_json::Value和SerdeParams在运行时才知道是不是对象，
+/// 需要展开时请先转换为IndexMap。
+///
+/// ```compile_fail
+/// use alipay_params::AlipayParams;
+///
+/// #[derive(AlipayParams)]
+/// struct Refund {
+///     #[alipay(flatten)]
+///     out_trade_no: String,
+/// }
+/// ```
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not converted to an object",
+    label = "not an object",
+    note = "#[alipay(flatten)] fields and tuple variants of tagged enums must be objects, such as structs with named fields, maps and key-value tuples"
+)]
+pub trait AlipayObject {}
+
+impl<K, V> AlipayObject for IndexMap<K, V> {}
+impl<K, V> AlipayObject for HashMap<K, V> {}
+impl<K, V> AlipayObject for BTreeMap<K, V> {}
+impl<K, V> AlipayObject for (K, V) {}
+impl<T: AlipayObject + ?Sized> AlipayObject for &T {}
+impl<T: AlipayObject + ?Sized> AlipayObject for Box<T> {}
+impl<T: AlipayObject + ?Sized> AlipayObject for Rc<T> {}
+/// None转换为Null，展开后没有字段
+impl<T: AlipayObject> AlipayObject for Option<T> {}
+
+/// derive宏在validate中检查展开的字段类型，编译错误会指向字段的类型
+pub fn assert_object<T: AlipayObject + ?Sized>() {}
+
+/// 展开字段的值，字段的类型已经通过assert_object检查
+pub fn flatten<T: AlipayParams>(value: T) -> IndexMap<String, AlipayValue> {
+    match value.to_alipay_value() {
+        AlipayValue::Null => IndexMap::new(),
+        value => match value.into_object() {
+            Some(map) => map,
+            None => panic!("AlipayObject must be converted to an object"),
+        },
+    }
+}
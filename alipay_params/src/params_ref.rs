@@ -0,0 +1,264 @@
+use crate::{
+    collection_to_alipay_value, json_value_to_alipay_value, AlipayParams, AlipayValue, IndexMap,
+    Money, SerdeParams,
+};
+use serde::Serialize;
+use serde_json::{Number, Value};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    rc::Rc,
+};
+
+/// 不消耗参数的转换，derive宏在设置`#[alipay(by_ref)]`后会同时实现AlipayParams和AlipayParamsRef
+///
+/// 实现了AlipayParamsRef的类型可以以引用的形式传给post等函数，
+/// 请求对象在请求之后还可以继续用于重试或者记录日志，不需要clone。
+///
+/// ```rust
+/// use alipay_params::{AlipayParams, AlipayParamsRef};
+///
+/// #[derive(AlipayParams)]
+/// #[alipay(by_ref)]
+/// struct TradeQuery {
+///     out_trade_no: String,
+/// }
+///
+/// let query = TradeQuery {
+///     out_trade_no: "20150320010101001".to_owned(),
+/// };
+/// let value = query.to_alipay_value_ref();
+/// // 引用也实现了AlipayParams
+/// assert_eq!((&query).to_alipay_value(), value);
+/// assert_eq!(query.out_trade_no, "20150320010101001");
+/// ```
+pub trait AlipayParamsRef {
+    fn to_alipay_value_ref(&self) -> AlipayValue;
+}
+
+impl<T> AlipayParams for &T
+where
+    T: AlipayParamsRef + AlipayParams,
+{
+    fn to_alipay_value(self) -> AlipayValue {
+        self.to_alipay_value_ref()
+    }
+    fn validate(&self) -> Result<(), crate::ValidationError> {
+        (**self).validate()
+    }
+}
+
+impl AlipayParams for &str {
+    fn to_alipay_value(self) -> AlipayValue {
+        AlipayValue::String(self.to_owned())
+    }
+}
+
+impl<T> AlipayParams for &[T]
+where
+    T: AlipayParamsRef + AlipayParams,
+{
+    fn to_alipay_value(self) -> AlipayValue {
+        self.to_alipay_value_ref()
+    }
+    fn validate(&self) -> Result<(), crate::ValidationError> {
+        crate::validate_collection(self)
+    }
+}
+
+impl<T> AlipayParamsRef for &T
+where
+    T: AlipayParamsRef + ?Sized,
+{
+    fn to_alipay_value_ref(&self) -> AlipayValue {
+        (**self).to_alipay_value_ref()
+    }
+}
+
+macro_rules! alipay_params_ref_implement_number {
+    ($($ty: ty),*) => {
+        $(
+            impl AlipayParamsRef for $ty {
+                fn to_alipay_value_ref(&self) -> AlipayValue {
+                    AlipayValue::Number(Number::from(*self))
+                }
+            }
+        )*
+    };
+}
+
+alipay_params_ref_implement_number!(u8, u16, u32, u64);
+alipay_params_ref_implement_number!(i8, i16, i32, i64);
+alipay_params_ref_implement_number!(usize, isize);
+
+macro_rules! alipay_params_ref_implement_copy {
+    ($($ty: ty),*) => {
+        $(
+            impl AlipayParamsRef for $ty {
+                fn to_alipay_value_ref(&self) -> AlipayValue {
+                    (*self).to_alipay_value()
+                }
+            }
+        )*
+    };
+}
+
+alipay_params_ref_implement_copy!(f32, f64, bool, char, (), Money);
+
+impl AlipayParamsRef for str {
+    fn to_alipay_value_ref(&self) -> AlipayValue {
+        AlipayValue::String(self.to_owned())
+    }
+}
+
+impl AlipayParamsRef for String {
+    fn to_alipay_value_ref(&self) -> AlipayValue {
+        AlipayValue::String(self.clone())
+    }
+}
+
+impl<T> AlipayParamsRef for Cow<'_, T>
+where
+    T: AlipayParamsRef + ToOwned + ?Sized,
+{
+    fn to_alipay_value_ref(&self) -> AlipayValue {
+        self.as_ref().to_alipay_value_ref()
+    }
+}
+
+impl<T> AlipayParamsRef for Box<T>
+where
+    T: AlipayParamsRef + ?Sized,
+{
+    fn to_alipay_value_ref(&self) -> AlipayValue {
+        (**self).to_alipay_value_ref()
+    }
+}
+
+impl<T> AlipayParamsRef for Rc<T>
+where
+    T: AlipayParamsRef + ?Sized,
+{
+    fn to_alipay_value_ref(&self) -> AlipayValue {
+        (**self).to_alipay_value_ref()
+    }
+}
+
+impl<T> AlipayParamsRef for Option<T>
+where
+    T: AlipayParamsRef,
+{
+    fn to_alipay_value_ref(&self) -> AlipayValue {
+        match self {
+            Some(v) => v.to_alipay_value_ref(),
+            None => AlipayValue::Null,
+        }
+    }
+}
+
+impl<T1, T2> AlipayParamsRef for (T1, T2)
+where
+    T1: AsRef<str>,
+    T2: AlipayParamsRef,
+{
+    fn to_alipay_value_ref(&self) -> AlipayValue {
+        AlipayValue::Tuple((
+            self.0.as_ref().to_owned(),
+            self.1.to_alipay_value_ref().to_json_value(),
+        ))
+    }
+}
+
+impl<T> AlipayParamsRef for [T]
+where
+    T: AlipayParamsRef,
+{
+    fn to_alipay_value_ref(&self) -> AlipayValue {
+        collection_to_alipay_value(self.iter().map(ByRef))
+    }
+}
+
+impl<T, const N: usize> AlipayParamsRef for [T; N]
+where
+    T: AlipayParamsRef,
+{
+    fn to_alipay_value_ref(&self) -> AlipayValue {
+        self.as_slice().to_alipay_value_ref()
+    }
+}
+
+impl<T> AlipayParamsRef for Vec<T>
+where
+    T: AlipayParamsRef,
+{
+    fn to_alipay_value_ref(&self) -> AlipayValue {
+        self.as_slice().to_alipay_value_ref()
+    }
+}
+
+impl<T> AlipayParamsRef for HashSet<T>
+where
+    T: AlipayParamsRef,
+{
+    fn to_alipay_value_ref(&self) -> AlipayValue {
+        collection_to_alipay_value(self.iter().map(ByRef))
+    }
+}
+
+macro_rules! alipay_params_ref_implement_map {
+    ($($map: ident),*) => {
+        $(
+            impl<T1, T2> AlipayParamsRef for $map<T1, T2>
+            where
+                T1: AsRef<str>,
+                T2: AlipayParamsRef,
+            {
+                fn to_alipay_value_ref(&self) -> AlipayValue {
+                    let mut map = IndexMap::new();
+                    for (key, val) in self {
+                        map.insert(key.as_ref().to_owned(), val.to_alipay_value_ref());
+                    }
+                    AlipayValue::Object(map)
+                }
+            }
+        )*
+    };
+}
+
+alipay_params_ref_implement_map!(IndexMap, HashMap, BTreeMap);
+
+impl AlipayParamsRef for Value {
+    fn to_alipay_value_ref(&self) -> AlipayValue {
+        json_value_to_alipay_value(self.clone())
+    }
+}
+
+impl AlipayParamsRef for AlipayValue {
+    fn to_alipay_value_ref(&self) -> AlipayValue {
+        self.clone()
+    }
+}
+
+impl<T> AlipayParamsRef for SerdeParams<T>
+where
+    T: Serialize,
+{
+    fn to_alipay_value_ref(&self) -> AlipayValue {
+        match self.try_to_alipay_value() {
+            Ok(value) => value,
+            Err(err) => panic!("SerdeParams cannot be serialized: {}", err),
+        }
+    }
+}
+
+/// 集合中的元素只需要实现AlipayParamsRef
+struct ByRef<'a, T: ?Sized>(&'a T);
+
+impl<T> AlipayParams for ByRef<'_, T>
+where
+    T: AlipayParamsRef + ?Sized,
+{
+    fn to_alipay_value(self) -> AlipayValue {
+        self.0.to_alipay_value_ref()
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{json_value_to_alipay_value, AlipayValue};
+use serde::{
+    ser::{SerializeMap, SerializeSeq},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_json::Value;
+use std::{fmt, str::FromStr};
+
+/// 按照biz_content中的json格式序列化，Tuple和TupleArray序列化为对象
+impl Serialize for AlipayValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            AlipayValue::Null => serializer.serialize_unit(),
+            AlipayValue::Bool(v) => serializer.serialize_bool(*v),
+            AlipayValue::Number(v) => v.serialize(serializer),
+            AlipayValue::String(v) => serializer.serialize_str(v),
+            AlipayValue::Tuple((key, val)) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(key, val)?;
+                map.end()
+            }
+            AlipayValue::TupleArray(v) => {
+                let mut map = serializer.serialize_map(Some(v.len()))?;
+                for (key, val) in v {
+                    map.serialize_entry(key, val)?;
+                }
+                map.end()
+            }
+            AlipayValue::Array(v) => {
+                let mut seq = serializer.serialize_seq(Some(v.len()))?;
+                for val in v {
+                    seq.serialize_element(val)?;
+                }
+                seq.end()
+            }
+            AlipayValue::Object(v) => {
+                let mut map = serializer.serialize_map(Some(v.len()))?;
+                for (key, val) in v {
+                    map.serialize_entry(key, val)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AlipayValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Value::deserialize(deserializer).map(json_value_to_alipay_value)
+    }
+}
+
+/// 输出json，`{:#}`输出格式化后的json
+///
+/// ```rust
+/// use alipay_params::AlipayValue;
+///
+/// let value: AlipayValue = r#"{"out_trade_no":"20150320010101001","total_amount":88.88}"#
+///     .parse()
+///     .unwrap();
+/// assert_eq!(value["out_trade_no"].as_str(), Some("20150320010101001"));
+/// assert_eq!(
+///     value.to_string(),
+///     r#"{"out_trade_no":"20150320010101001","total_amount":88.88}"#
+/// );
+/// ```
+impl fmt::Display for AlipayValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let json = if f.alternate() {
+            serde_json::to_string_pretty(self)
+        } else {
+            serde_json::to_string(self)
+        };
+        f.write_str(&json.map_err(|_| fmt::Error)?)
+    }
+}
+
+/// 从json字符串解析
+impl FromStr for AlipayValue {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IndexMap;
+    use serde_json::json;
+
+    fn order() -> AlipayValue {
+        AlipayValue::Object(IndexMap::from([
+            (
+                "out_trade_no".to_owned(),
+                AlipayValue::from("20150320010101001"),
+            ),
+            (
+                "goods_detail".to_owned(),
+                AlipayValue::Array(vec![AlipayValue::Object(IndexMap::from([
+                    ("goods_id".to_owned(), AlipayValue::from("apple-01")),
+                    ("quantity".to_owned(), AlipayValue::from(1)),
+                ]))]),
+            ),
+            (
+                "extend_params".to_owned(),
+                AlipayValue::TupleArray(vec![
+                    (
+                        "sys_service_provider_id".to_owned(),
+                        json!("2088511833207846"),
+                    ),
+                    ("hb_fq_num".to_owned(), json!(3)),
+                ]),
+            ),
+            (
+                "royalty".to_owned(),
+                AlipayValue::Tuple(("royalty_type".to_owned(), json!("ROYALTY"))),
+            ),
+            ("body".to_owned(), AlipayValue::Null),
+            ("paid".to_owned(), AlipayValue::from(false)),
+        ]))
+    }
+
+    const ORDER: &str = r#"{"out_trade_no":"20150320010101001","goods_detail":[{"goods_id":"apple-01","quantity":1}],"extend_params":{"sys_service_provider_id":"2088511833207846","hb_fq_num":3},"royalty":{"royalty_type":"ROYALTY"},"body":null,"paid":false}"#;
+
+    #[test]
+    fn serialize_round_trip() {
+        let json = serde_json::to_string(&order()).unwrap();
+        assert_eq!(json, ORDER);
+
+        // 解析后tuple变为对象，序列化的结果不变
+        let value: AlipayValue = serde_json::from_str(&json).unwrap();
+        assert!(value["extend_params"].is_object());
+        assert!(value["body"].is_null());
+        assert_eq!(value["goods_detail"][0]["quantity"].as_u64(), Some(1));
+        assert_eq!(serde_json::to_string(&value).unwrap(), ORDER);
+        assert_eq!(value.to_json_value(), order().to_json_value());
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(order().to_string(), ORDER);
+        assert_eq!(AlipayValue::Null.to_string(), "null");
+        let pretty = format!("{:#}", AlipayValue::Tuple(("a".to_owned(), json!([1, 2]))));
+        assert_eq!(pretty, "{\n  \"a\": [\n    1,\n    2\n  ]\n}");
+        assert_eq!(
+            format!("{:#}", order()),
+            serde_json::to_string_pretty(&order().to_json_value()).unwrap()
+        );
+    }
+
+    #[test]
+    fn from_str() {
+        let value: AlipayValue = ORDER.parse().unwrap();
+        assert_eq!(value.to_string(), ORDER);
+        assert_eq!("null".parse::<AlipayValue>().unwrap(), AlipayValue::Null);
+        assert_eq!(
+            "[1,\"a\"]".parse::<AlipayValue>().unwrap(),
+            AlipayValue::Array(vec![AlipayValue::from(1), AlipayValue::from("a")])
+        );
+
+        for invalid in ["", "{", r#"{"a":}"#, "out_trade_no=1", "[1,]"] {
+            assert!(invalid.parse::<AlipayValue>().is_err(), "{}", invalid);
+        }
+        let err = r#"{"a":1"#.parse::<AlipayValue>().unwrap_err();
+        assert!(err.is_eof());
+    }
+}
@@ -0,0 +1,236 @@
+use crate::Money;
+use std::{borrow::Cow, error::Error, fmt};
+
+/// 校验规则，对应derive宏中的`#[alipay(...)]`属性
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationRule {
+    /// `#[alipay(max_len = 64)]`，按字符数计算
+    MaxLen(usize),
+    /// `#[alipay(range(min = 0.01, max = 100000000))]`
+    Range { min: Option<f64>, max: Option<f64> },
+    /// `#[alipay(pattern = "^[0-9a-zA-Z_]+$")]`
+    Pattern(String),
+    /// `#[alipay(required)]`，None、空字符串和空数组都视为缺失
+    Required,
+    /// SerdeParams序列化失败，内容为serde的错误信息
+    Serialize(String),
+}
+
+/// 参数校验失败
+///
+/// field是出错的参数名，嵌套结构体中的字段用`.`连接，比如`payee_info.identity`。
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    pub field: String,
+    pub rule: ValidationRule,
+}
+
+impl ValidationError {
+    pub fn new<S: Into<String>>(field: S, rule: ValidationRule) -> Self {
+        ValidationError {
+            field: field.into(),
+            rule,
+        }
+    }
+    /// 在字段名前加上外层的字段名，参数本身出错时field为空
+    pub fn nested(mut self, parent: &str) -> Self {
+        self.field = if self.field.is_empty() {
+            parent.to_owned()
+        } else {
+            format!("{}.{}", parent, self.field)
+        };
+        self
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 参数本身出错时没有字段名
+        let field = if self.field.is_empty() {
+            "params"
+        } else {
+            self.field.as_str()
+        };
+        match &self.rule {
+            ValidationRule::MaxLen(len) => {
+                write!(f, "{} is longer than {} characters", field, len)
+            }
+            ValidationRule::Range { min, max } => {
+                write!(f, "{} is out of range [", field)?;
+                if let Some(min) = min {
+                    write!(f, "{}", min)?;
+                }
+                write!(f, ", ")?;
+                if let Some(max) = max {
+                    write!(f, "{}", max)?;
+                }
+                write!(f, "]")
+            }
+            ValidationRule::Pattern(pattern) => {
+                write!(f, "{} does not match pattern {}", field, pattern)
+            }
+            ValidationRule::Required => write!(f, "{} is required", field),
+            ValidationRule::Serialize(message) => {
+                write!(f, "{} cannot be serialized: {}", field, message)
+            }
+        }
+    }
+}
+
+impl Error for ValidationError {}
+
+/// 校验属性所需要的字段信息，derive宏生成的validate会调用
+///
+/// 返回None表示该规则不适用于当前值，比如值为None时不检查长度。
+pub trait ValidateField {
+    /// max_len使用的长度
+    fn validated_len(&self) -> Option<usize> {
+        None
+    }
+    /// range使用的数值，字符串无法解析时返回NaN，校验一定失败
+    fn validated_number(&self) -> Option<f64> {
+        None
+    }
+    /// pattern使用的字符串
+    fn validated_str(&self) -> Option<&str> {
+        None
+    }
+    /// required使用，值是否存在
+    fn is_present(&self) -> bool {
+        true
+    }
+}
+
+impl ValidateField for str {
+    fn validated_len(&self) -> Option<usize> {
+        Some(self.chars().count())
+    }
+    fn validated_number(&self) -> Option<f64> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.parse().unwrap_or(f64::NAN))
+        }
+    }
+    fn validated_str(&self) -> Option<&str> {
+        Some(self)
+    }
+    fn is_present(&self) -> bool {
+        !self.is_empty()
+    }
+}
+
+impl ValidateField for String {
+    fn validated_len(&self) -> Option<usize> {
+        self.as_str().validated_len()
+    }
+    fn validated_number(&self) -> Option<f64> {
+        self.as_str().validated_number()
+    }
+    fn validated_str(&self) -> Option<&str> {
+        Some(self)
+    }
+    fn is_present(&self) -> bool {
+        !self.is_empty()
+    }
+}
+
+impl<T> ValidateField for &T
+where
+    T: ValidateField + ?Sized,
+{
+    fn validated_len(&self) -> Option<usize> {
+        (**self).validated_len()
+    }
+    fn validated_number(&self) -> Option<f64> {
+        (**self).validated_number()
+    }
+    fn validated_str(&self) -> Option<&str> {
+        (**self).validated_str()
+    }
+    fn is_present(&self) -> bool {
+        (**self).is_present()
+    }
+}
+
+impl<T> ValidateField for Box<T>
+where
+    T: ValidateField + ?Sized,
+{
+    fn validated_len(&self) -> Option<usize> {
+        (**self).validated_len()
+    }
+    fn validated_number(&self) -> Option<f64> {
+        (**self).validated_number()
+    }
+    fn validated_str(&self) -> Option<&str> {
+        (**self).validated_str()
+    }
+    fn is_present(&self) -> bool {
+        (**self).is_present()
+    }
+}
+
+impl ValidateField for Cow<'_, str> {
+    fn validated_len(&self) -> Option<usize> {
+        self.as_ref().validated_len()
+    }
+    fn validated_number(&self) -> Option<f64> {
+        self.as_ref().validated_number()
+    }
+    fn validated_str(&self) -> Option<&str> {
+        Some(self)
+    }
+    fn is_present(&self) -> bool {
+        !self.is_empty()
+    }
+}
+
+impl<T> ValidateField for Option<T>
+where
+    T: ValidateField,
+{
+    fn validated_len(&self) -> Option<usize> {
+        self.as_ref()?.validated_len()
+    }
+    fn validated_number(&self) -> Option<f64> {
+        self.as_ref()?.validated_number()
+    }
+    fn validated_str(&self) -> Option<&str> {
+        self.as_ref()?.validated_str()
+    }
+    fn is_present(&self) -> bool {
+        self.as_ref().is_some_and(|v| v.is_present())
+    }
+}
+
+impl<T> ValidateField for Vec<T> {
+    fn validated_len(&self) -> Option<usize> {
+        Some(self.len())
+    }
+    fn is_present(&self) -> bool {
+        !self.is_empty()
+    }
+}
+
+impl ValidateField for Money {
+    fn validated_number(&self) -> Option<f64> {
+        Some(self.fen() as f64 / 100.0)
+    }
+}
+
+macro_rules! validate_field_implement_number {
+    ($($ty: ty),*) => {
+        $(
+            impl ValidateField for $ty {
+                fn validated_number(&self) -> Option<f64> {
+                    Some(*self as f64)
+                }
+            }
+        )*
+    };
+}
+
+validate_field_implement_number!(u8, u16, u32, u64, usize);
+validate_field_implement_number!(i8, i16, i32, i64, isize);
+validate_field_implement_number!(f32, f64);
@@ -0,0 +1,352 @@
+use alipay_params::{AlipayParams, AlipayParamsRef, AlipayValue, IndexMap, ValidationRule};
+use serde_json::json;
+
+fn to_json<T: AlipayParams>(params: T) -> serde_json::Value {
+    params.to_alipay_value().to_json_value()
+}
+
+#[derive(AlipayParams)]
+struct Goods {
+    goods_id: String,
+    #[alipay(rename = "goodsName")]
+    goods_name: String,
+    r#type: u32,
+    #[alipay(skip)]
+    #[allow(dead_code)]
+    cache: u32,
+    #[alipay(skip_if_none)]
+    body: Option<String>,
+    show_url: Option<String>,
+}
+
+#[test]
+fn field_attributes() {
+    let goods = Goods {
+        goods_id: "apple-01".to_owned(),
+        goods_name: "ipad".to_owned(),
+        r#type: 1,
+        cache: 42,
+        body: None,
+        show_url: None,
+    };
+    // 没有属性的字段使用字段名，r#前缀会被去掉，None默认输出null
+    assert_eq!(
+        to_json(goods),
+        json!({"goods_id": "apple-01", "goodsName": "ipad", "type": 1, "show_url": null})
+    );
+}
+
+#[test]
+fn skip_if_none_keeps_some() {
+    let goods = Goods {
+        goods_id: "apple-01".to_owned(),
+        goods_name: "ipad".to_owned(),
+        r#type: 1,
+        cache: 42,
+        body: Some("pad".to_owned()),
+        show_url: None,
+    };
+    assert_eq!(to_json(goods)["body"], json!("pad"));
+}
+
+#[derive(AlipayParams)]
+#[alipay(rename_all = "camelCase")]
+struct Camel {
+    out_trade_no: String,
+    #[alipay(rename = "total_amount")]
+    total_amount: String,
+}
+
+#[test]
+fn rename_all() {
+    let value = to_json(Camel {
+        out_trade_no: "1".to_owned(),
+        total_amount: "0.01".to_owned(),
+    });
+    assert_eq!(value, json!({"outTradeNo": "1", "total_amount": "0.01"}));
+}
+
+#[derive(AlipayParams, Clone)]
+#[alipay(by_ref)]
+struct Extend {
+    sys_service_provider_id: String,
+}
+
+#[derive(AlipayParams, Clone)]
+#[alipay(by_ref)]
+struct Pay {
+    subject: String,
+    #[alipay(flatten)]
+    extend: Extend,
+    #[alipay(flatten)]
+    optional: Option<Extend>,
+    #[alipay(flatten)]
+    other: IndexMap<String, AlipayValue>,
+    #[alipay(flatten)]
+    pair: (&'static str, u32),
+}
+
+#[test]
+fn flatten_objects() {
+    let pay = Pay {
+        subject: "iphone".to_owned(),
+        extend: Extend {
+            sys_service_provider_id: "2088511833207846".to_owned(),
+        },
+        optional: None,
+        other: IndexMap::from([("timeout_express".to_owned(), AlipayValue::from("90m"))]),
+        pair: ("quantity", 2),
+    };
+    let expected = json!({
+        "subject": "iphone",
+        "sys_service_provider_id": "2088511833207846",
+        "timeout_express": "90m",
+        "quantity": 2,
+    });
+    assert_eq!(pay.to_alipay_value_ref().to_json_value(), expected);
+    assert_eq!(to_json(pay), expected);
+}
+
+#[test]
+fn biz_content_keeps_declaration_order() {
+    let pay = Pay {
+        subject: "iphone".to_owned(),
+        extend: Extend {
+            sys_service_provider_id: "2088511833207846".to_owned(),
+        },
+        optional: None,
+        other: IndexMap::from([
+            ("timeout_express".to_owned(), AlipayValue::from("90m")),
+            ("body".to_owned(), AlipayValue::from("pad")),
+        ]),
+        pair: ("quantity", 2),
+    };
+    // json!比较时不区分key的顺序，这里比较序列化后的字符串
+    let expected = r#"{"subject":"iphone","sys_service_provider_id":"2088511833207846","timeout_express":"90m","body":"pad","quantity":2}"#;
+    assert_eq!(
+        serde_json::to_string(&pay.to_alipay_value_ref().to_json_value()).unwrap(),
+        expected
+    );
+    assert_eq!(pay.to_alipay_value().to_string(), expected);
+
+    let goods = Goods {
+        goods_id: "apple-01".to_owned(),
+        goods_name: "ipad".to_owned(),
+        r#type: 1,
+        cache: 42,
+        body: Some("pad".to_owned()),
+        show_url: None,
+    };
+    assert_eq!(
+        serde_json::to_string(&to_json(goods)).unwrap(),
+        r#"{"goods_id":"apple-01","goodsName":"ipad","type":1,"body":"pad","show_url":null}"#
+    );
+}
+
+#[derive(AlipayParams, PartialEq, Eq)]
+#[alipay(rename_all = "SCREAMING_SNAKE_CASE")]
+enum TradeStatus {
+    WaitBuyerPay,
+    #[alipay(rename = "TRADE_SUCCESS")]
+    Success,
+}
+
+#[derive(AlipayParams)]
+struct OutTradeNo(String);
+
+#[derive(AlipayParams)]
+struct Pair(u32, &'static str);
+
+#[derive(AlipayParams)]
+struct Nothing;
+
+#[test]
+fn unit_enums_and_tuple_structs() {
+    assert_eq!(to_json(TradeStatus::WaitBuyerPay), json!("WAIT_BUYER_PAY"));
+    assert_eq!(to_json(TradeStatus::Success), json!("TRADE_SUCCESS"));
+    assert_eq!(to_json(OutTradeNo("1".to_owned())), json!("1"));
+    assert_eq!(to_json(Pair(1, "a")), json!([1, "a"]));
+    assert_eq!(to_json(Nothing), json!(null));
+}
+
+#[derive(AlipayParams)]
+#[alipay(rename_all = "snake_case")]
+enum Identity {
+    UserId(String),
+    Certificate { cert_no: String, cert_type: String },
+}
+
+#[test]
+fn externally_tagged_enum() {
+    assert_eq!(
+        to_json(Identity::UserId("2088".to_owned())),
+        json!({"user_id": "2088"})
+    );
+    assert_eq!(
+        to_json(Identity::Certificate {
+            cert_no: "1".to_owned(),
+            cert_type: "IDENTITY_CARD".to_owned(),
+        }),
+        json!({"certificate": {"cert_no": "1", "cert_type": "IDENTITY_CARD"}})
+    );
+}
+
+#[derive(AlipayParams)]
+#[alipay(by_ref, tag = "identity_type", rename_all = "SCREAMING_SNAKE_CASE")]
+enum Payee {
+    Anonymous,
+    AlipayUserId { identity: String },
+    Extend(Extend),
+    Map(IndexMap<String, String>),
+}
+
+#[test]
+fn internally_tagged_enum() {
+    assert_eq!(
+        to_json(Payee::Anonymous),
+        json!({"identity_type": "ANONYMOUS"})
+    );
+    assert_eq!(
+        to_json(Payee::AlipayUserId {
+            identity: "2088".to_owned()
+        }),
+        json!({"identity_type": "ALIPAY_USER_ID", "identity": "2088"})
+    );
+    let extend = Payee::Extend(Extend {
+        sys_service_provider_id: "2088".to_owned(),
+    });
+    let expected = json!({"sys_service_provider_id": "2088", "identity_type": "EXTEND"});
+    assert_eq!(extend.to_alipay_value_ref().to_json_value(), expected);
+    assert_eq!(to_json(extend), expected);
+    assert_eq!(
+        to_json(Payee::Map(IndexMap::new())),
+        json!({"identity_type": "MAP"})
+    );
+}
+
+#[derive(AlipayParams)]
+enum Scene {
+    #[alipay(rename = "bar_code")]
+    BarCode {
+        #[alipay(required, max_len = 4)]
+        auth_code: String,
+    },
+}
+
+#[test]
+fn enum_variant_validation() {
+    let err = Scene::BarCode {
+        auth_code: String::new(),
+    }
+    .validate()
+    .unwrap_err();
+    assert_eq!(err.field, "auth_code");
+    assert_eq!(err.rule, ValidationRule::Required);
+    assert!(Scene::BarCode {
+        auth_code: "2808".to_owned()
+    }
+    .validate()
+    .is_ok());
+}
+
+#[derive(AlipayParams)]
+struct Refund {
+    #[alipay(pattern = "^[0-9a-zA-Z_]+$")]
+    out_request_no: String,
+    #[alipay(rename = "storeId", pattern = "^S[0-9]{3}$")]
+    store_id: Option<String>,
+}
+
+fn refund(out_request_no: &str, store_id: Option<&str>) -> Refund {
+    Refund {
+        out_request_no: out_request_no.to_owned(),
+        store_id: store_id.map(|v| v.to_owned()),
+    }
+}
+
+#[test]
+fn pattern_accepts_matching_values() {
+    assert!(refund("HZ01RF001", None).validate().is_ok());
+    assert!(refund("HZ01_RF001", Some("S001")).validate().is_ok());
+}
+
+#[test]
+fn pattern_rejects_other_values() {
+    let err = refund("HZ01-RF001", None).validate().unwrap_err();
+    assert_eq!(err.field, "out_request_no");
+    assert_eq!(
+        err.rule,
+        ValidationRule::Pattern("^[0-9a-zA-Z_]+$".to_owned())
+    );
+    assert_eq!(
+        err.to_string(),
+        "out_request_no does not match pattern ^[0-9a-zA-Z_]+$"
+    );
+
+    // 错误中的字段名是重命名后的参数名
+    let err = refund("HZ01RF001", Some("S1")).validate().unwrap_err();
+    assert_eq!(err.field, "storeId");
+    // 嵌套时带上外层的字段名
+    let err = vec![refund("HZ01RF001", None), refund("", Some("s001"))]
+        .validate()
+        .unwrap_err();
+    assert_eq!(err.field, "1.out_request_no");
+}
+
+#[test]
+fn containers_validate_required_fields() {
+    let invalid = || Scene::BarCode {
+        auth_code: String::new(),
+    };
+    let is_required = |err: alipay_params::ValidationError| err.rule == ValidationRule::Required;
+    assert!(is_required(("scene", invalid()).validate().unwrap_err()));
+    assert!(is_required(
+        IndexMap::from([("scene", invalid())])
+            .validate()
+            .unwrap_err()
+    ));
+    assert!(is_required(
+        std::collections::HashMap::from([("scene", invalid())])
+            .validate()
+            .unwrap_err()
+    ));
+    assert!(is_required(
+        std::collections::BTreeMap::from([("scene", invalid())])
+            .validate()
+            .unwrap_err()
+    ));
+    assert!(is_required(
+        std::rc::Rc::new(vec![invalid()]).validate().unwrap_err()
+    ));
+}
+
+/// 只实现了AlipayParams的字段类型
+struct Custom;
+
+impl AlipayParams for Custom {
+    fn to_alipay_value(self) -> AlipayValue {
+        AlipayValue::from("custom")
+    }
+}
+
+#[derive(AlipayParams)]
+struct WithCustom {
+    custom: Custom,
+}
+
+#[test]
+fn fields_only_need_alipay_params() {
+    assert_eq!(
+        to_json(WithCustom { custom: Custom }),
+        json!({"custom": "custom"})
+    );
+}
+
+#[test]
+fn by_ref_references_are_params() {
+    let extend = Extend {
+        sys_service_provider_id: "2088".to_owned(),
+    };
+    assert_eq!(to_json(&extend), json!({"sys_service_provider_id": "2088"}));
+    assert_eq!(extend.sys_service_provider_id, "2088");
+}
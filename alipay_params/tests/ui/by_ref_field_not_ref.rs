@@ -0,0 +1,29 @@
+use alipay_params::{AlipayParams, AlipayParamsRef, AlipayValue};
+
+struct Custom;
+
+impl AlipayParams for Custom {
+    fn to_alipay_value(self) -> AlipayValue {
+        AlipayValue::Null
+    }
+}
+
+#[derive(AlipayParams)]
+#[alipay(by_ref)]
+struct Request {
+    custom: Custom,
+}
+
+#[derive(AlipayParams)]
+struct Query {
+    out_trade_no: String,
+}
+
+fn by_ref<T: AlipayParamsRef>(_: &T) {}
+
+fn main() {
+    // 没有#[alipay(by_ref)]时不实现AlipayParamsRef
+    by_ref(&Query {
+        out_trade_no: "1".to_owned(),
+    });
+}
@@ -0,0 +1,11 @@
+use alipay_params::{AlipayParams, AlipayValue};
+
+#[derive(AlipayParams)]
+struct Refund {
+    #[alipay(flatten)]
+    out_trade_no: String,
+    #[alipay(flatten)]
+    extend_params: AlipayValue,
+}
+
+fn main() {}
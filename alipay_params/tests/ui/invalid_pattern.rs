@@ -0,0 +1,9 @@
+use alipay_params::AlipayParams;
+
+#[derive(AlipayParams)]
+struct Refund {
+    #[alipay(pattern = "^[0-9a-zA-Z_+$")]
+    out_request_no: String,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use alipay_params::AlipayParams;
+
+#[derive(AlipayParams)]
+#[alipay(tag = "type")]
+enum Identity {
+    UserId(String),
+}
+
+fn main() {}
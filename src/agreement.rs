@@ -0,0 +1,386 @@
+//! 周期扣款（商家扣款）协议相关的接口，alipay.user.agreement.*
+
+use crate::{
+    error::AlipayResult, notify, response::Empty, trade::TradeDetail, AlipayParams, Cli, Money,
+    Sign,
+};
+use futures::future::{BoxFuture, FutureExt};
+use serde::Deserialize;
+
+/// 周期类型
+#[derive(AlipayParams, Debug, Clone, Copy, PartialEq, Eq)]
+#[alipay(by_ref, rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PeriodType {
+    Day,
+    Month,
+}
+
+/// 周期扣款规则
+#[derive(AlipayParams, Debug, Clone)]
+#[alipay(by_ref)]
+pub struct PeriodRule {
+    pub period_type: PeriodType,
+    /// 周期数，DAY时不能小于7
+    #[alipay(range(min = 1))]
+    pub period: u32,
+    /// 首次扣款时间，yyyy-MM-dd
+    #[alipay(required)]
+    pub execute_time: String,
+    /// 单次扣款最大金额
+    pub single_amount: Money,
+    #[alipay(skip_if_none)]
+    pub total_amount: Option<Money>,
+    #[alipay(skip_if_none)]
+    pub total_payments: Option<u32>,
+}
+
+/// 签约的接入渠道
+#[derive(AlipayParams, Debug, Clone)]
+#[alipay(by_ref)]
+pub struct AccessParams {
+    pub channel: String,
+}
+
+/// 页面签约，alipay.user.agreement.page.sign
+///
+/// notify_url、return_url是公共参数，需要时通过set_public_params设置。
+#[derive(AlipayParams, Debug, Clone)]
+#[alipay(by_ref)]
+pub struct AgreementSignRequest {
+    #[alipay(required)]
+    pub personal_product_code: String,
+    #[alipay(required)]
+    pub sign_scene: String,
+    #[alipay(required)]
+    pub product_code: String,
+    /// 商户签约号，同一个用户在同一个场景下唯一
+    #[alipay(skip_if_none, max_len = 32)]
+    pub external_agreement_no: Option<String>,
+    #[alipay(skip_if_none)]
+    pub external_logon_id: Option<String>,
+    pub access_params: AccessParams,
+    #[alipay(skip_if_none)]
+    pub period_rule_params: Option<PeriodRule>,
+    /// 协议有效期，比如2m表示2个月
+    #[alipay(skip_if_none)]
+    pub sign_validity_period: Option<String>,
+}
+
+impl AgreementSignRequest {
+    /// 周期扣款，personal_product_code为CYCLE_PAY_AUTH_P，product_code为CYCLE_PAY_AUTH，渠道为ALIPAYAPP
+    pub fn cycle_pay(sign_scene: &str, external_agreement_no: &str, rule: PeriodRule) -> Self {
+        AgreementSignRequest {
+            personal_product_code: "CYCLE_PAY_AUTH_P".to_owned(),
+            sign_scene: sign_scene.to_owned(),
+            product_code: "CYCLE_PAY_AUTH".to_owned(),
+            external_agreement_no: Some(external_agreement_no.to_owned()),
+            external_logon_id: None,
+            access_params: AccessParams {
+                channel: "ALIPAYAPP".to_owned(),
+            },
+            period_rule_params: Some(rule),
+            sign_validity_period: None,
+        }
+    }
+}
+
+/// 查询或者解约时指定协议，支付宝协议号和商户签约号二选一
+#[derive(AlipayParams, Debug, Clone, Default)]
+#[alipay(by_ref)]
+pub struct AgreementKey {
+    #[alipay(skip_if_none)]
+    pub agreement_no: Option<String>,
+    #[alipay(skip_if_none)]
+    pub external_agreement_no: Option<String>,
+    #[alipay(skip_if_none)]
+    pub personal_product_code: Option<String>,
+    #[alipay(skip_if_none)]
+    pub sign_scene: Option<String>,
+}
+
+impl AgreementKey {
+    pub fn agreement_no(agreement_no: &str) -> Self {
+        AgreementKey {
+            agreement_no: Some(agreement_no.to_owned()),
+            ..Default::default()
+        }
+    }
+    /// 通过商户签约号指定协议时需要签约时的产品码和场景
+    pub fn external(
+        external_agreement_no: &str,
+        personal_product_code: &str,
+        sign_scene: &str,
+    ) -> Self {
+        AgreementKey {
+            agreement_no: None,
+            external_agreement_no: Some(external_agreement_no.to_owned()),
+            personal_product_code: Some(personal_product_code.to_owned()),
+            sign_scene: Some(sign_scene.to_owned()),
+        }
+    }
+}
+
+/// 协议状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AgreementStatus {
+    /// 暂存，协议未生效
+    Temp,
+    /// 正常
+    Normal,
+    /// 已解约
+    Stop,
+    #[serde(other)]
+    Unknown,
+}
+
+/// 协议详情，alipay.user.agreement.query
+#[derive(Debug, Clone, Deserialize)]
+pub struct AgreementDetail {
+    pub agreement_no: String,
+    pub status: AgreementStatus,
+    #[serde(default)]
+    pub external_agreement_no: Option<String>,
+    #[serde(default)]
+    pub personal_product_code: Option<String>,
+    #[serde(default)]
+    pub sign_scene: Option<String>,
+    #[serde(default)]
+    pub sign_time: Option<String>,
+    #[serde(default)]
+    pub valid_time: Option<String>,
+    #[serde(default)]
+    pub invalid_time: Option<String>,
+    #[serde(default)]
+    pub alipay_logon_id: Option<String>,
+    #[serde(default)]
+    pub principal_id: Option<String>,
+}
+
+/// 协议扣款中的协议参数
+#[derive(AlipayParams, Debug, Clone)]
+#[alipay(by_ref)]
+pub struct AgreementParams {
+    #[alipay(required)]
+    pub agreement_no: String,
+}
+
+/// 协议扣款，product_code为GENERAL_WITHHOLDING的alipay.trade.pay
+#[derive(AlipayParams, Debug, Clone)]
+#[alipay(by_ref)]
+pub struct AgreementPayRequest {
+    #[alipay(required, max_len = 64)]
+    pub out_trade_no: String,
+    pub total_amount: Money,
+    #[alipay(required, max_len = 256)]
+    pub subject: String,
+    pub product_code: String,
+    pub agreement_params: AgreementParams,
+}
+
+impl AgreementPayRequest {
+    pub fn new(out_trade_no: &str, subject: &str, total_amount: Money, agreement_no: &str) -> Self {
+        AgreementPayRequest {
+            out_trade_no: out_trade_no.to_owned(),
+            total_amount,
+            subject: subject.to_owned(),
+            product_code: "GENERAL_WITHHOLDING".to_owned(),
+            agreement_params: AgreementParams {
+                agreement_no: agreement_no.to_owned(),
+            },
+        }
+    }
+}
+
+/// 通知类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum NotifyType {
+    /// 签约
+    #[serde(rename = "dut_user_sign")]
+    Sign,
+    /// 解约
+    #[serde(rename = "dut_user_unsign")]
+    Unsign,
+    #[serde(other)]
+    Unknown,
+}
+
+/// 签约、解约的异步通知
+#[derive(Debug, Clone, Deserialize)]
+pub struct AgreementNotify {
+    pub notify_id: String,
+    pub notify_time: String,
+    pub notify_type: NotifyType,
+    pub agreement_no: String,
+    pub status: AgreementStatus,
+    #[serde(default)]
+    pub external_agreement_no: Option<String>,
+    #[serde(default)]
+    pub personal_product_code: Option<String>,
+    #[serde(default)]
+    pub sign_scene: Option<String>,
+    #[serde(default)]
+    pub sign_time: Option<String>,
+    #[serde(default)]
+    pub unsign_time: Option<String>,
+    #[serde(default)]
+    pub alipay_user_id: Option<String>,
+    #[serde(default)]
+    pub alipay_logon_id: Option<String>,
+}
+
+/// 验签并解析签约、解约通知，body为通知的表单内容，处理成功后需要返回success
+pub fn verify_notify<S: Sign + ?Sized>(client: &S, body: &str) -> AlipayResult<AgreementNotify> {
+    notify::parse(client, body)
+}
+
+/// 周期扣款协议接口
+///
+/// ```rust,no_run
+/// use alipay_rs::agreement::{AgreementApi, AgreementSignRequest, PeriodRule, PeriodType};
+///
+/// # fn sign(client: &alipay_rs::Client) -> alipay_rs::error::AlipayResult<()> {
+/// let rule = PeriodRule {
+///     period_type: PeriodType::Month,
+///     period: 1,
+///     execute_time: "2024-01-01".to_owned(),
+///     single_amount: "30.00".parse()?,
+///     total_amount: None,
+///     total_payments: None,
+/// };
+/// let request = AgreementSignRequest::cycle_pay("INDUSTRY|DIGITAL_MEDIA", "202401010001", rule);
+/// // 把用户重定向到这个地址完成签约
+/// let url = client.sign_url(&request)?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait AgreementApi {
+    /// 生成页面签约的跳转地址
+    fn sign_url(&self, request: &AgreementSignRequest) -> AlipayResult<String>;
+    /// 查询协议
+    fn agreement_query<'a>(
+        &'a self,
+        key: &'a AgreementKey,
+    ) -> BoxFuture<'a, AlipayResult<AgreementDetail>>;
+    /// 解约
+    fn agreement_unsign<'a>(&'a self, key: &'a AgreementKey) -> BoxFuture<'a, AlipayResult<()>>;
+    /// 根据协议扣款
+    fn agreement_pay<'a>(
+        &'a self,
+        request: &'a AgreementPayRequest,
+    ) -> BoxFuture<'a, AlipayResult<TradeDetail>>;
+}
+
+impl<C> AgreementApi for C
+where
+    C: Cli + Sync,
+{
+    fn sign_url(&self, request: &AgreementSignRequest) -> AlipayResult<String> {
+        self.generate_url("alipay.user.agreement.page.sign", request)
+    }
+
+    fn agreement_query<'a>(
+        &'a self,
+        key: &'a AgreementKey,
+    ) -> BoxFuture<'a, AlipayResult<AgreementDetail>> {
+        async move {
+            let method = "alipay.user.agreement.query";
+            let response = self.post(method, key).await?;
+            response.biz_response(method)?.into_result()
+        }
+        .boxed()
+    }
+
+    fn agreement_unsign<'a>(&'a self, key: &'a AgreementKey) -> BoxFuture<'a, AlipayResult<()>> {
+        async move {
+            let method = "alipay.user.agreement.unsign";
+            let response = self.post(method, key).await?;
+            response.biz_response::<Empty>(method)?.into_result()?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn agreement_pay<'a>(
+        &'a self,
+        request: &'a AgreementPayRequest,
+    ) -> BoxFuture<'a, AlipayResult<TradeDetail>> {
+        async move {
+            let method = "alipay.trade.pay";
+            let response = self.post(method, request).await?;
+            response.biz_response(method)?.into_result()
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{client, rsa_signer, serve_biz};
+    use futures::executor::block_on;
+
+    const QUERY: &str = "alipay.user.agreement.query";
+
+    #[test]
+    fn agreement_query() {
+        let (url, handle) = serve_biz(&[(
+            QUERY,
+            r#"{"code":"10000","msg":"Success","agreement_no":"20215803004813538774","status":"NORMAL","sign_scene":"INDUSTRY|DIGITAL_MEDIA"}"#,
+        )]);
+        let key = AgreementKey::agreement_no("20215803004813538774");
+        let detail = block_on(client(&url).agreement_query(&key)).unwrap();
+        handle.join().unwrap();
+        assert_eq!(detail.agreement_no, "20215803004813538774");
+        assert_eq!(detail.status, AgreementStatus::Normal);
+    }
+
+    #[test]
+    fn agreement_query_errors_are_biz_errors() {
+        let (url, handle) = serve_biz(&[(
+            QUERY,
+            r#"{"code":"40004","msg":"Business Failed","sub_code":"USER_AGREEMENT_NOT_EXIST","sub_msg":"用户协议不存在"}"#,
+        )]);
+        let key = AgreementKey::agreement_no("20215803004813538774");
+        let err = block_on(client(&url).agreement_query(&key)).unwrap_err();
+        handle.join().unwrap();
+        let biz = err.biz_error().unwrap();
+        assert_eq!(biz.code, "40004");
+        assert_eq!(biz.sub_code, "USER_AGREEMENT_NOT_EXIST");
+    }
+
+    #[test]
+    fn verify_sign_notify() {
+        let signer = rsa_signer();
+        let mut params = vec![
+            ("notify_id", "ac05099524730693a8b330c5ecf72da9786"),
+            ("notify_time", "2024-01-01 10:00:00"),
+            ("notify_type", "dut_user_sign"),
+            ("agreement_no", "20215803004813538774"),
+            ("status", "NORMAL"),
+            ("external_agreement_no", "202401010001"),
+        ];
+        let mut content = params.clone();
+        content.sort();
+        let content = content
+            .iter()
+            .map(|(key, val)| format!("{}={}", key, val))
+            .collect::<Vec<_>>()
+            .join("&");
+        let sign = signer.sign(&content).unwrap();
+        params.push(("sign_type", "RSA2"));
+        params.push(("sign", &sign));
+        let body = serde_urlencoded::to_string(&params).unwrap();
+
+        let notify = verify_notify(&signer, &body).unwrap();
+        assert_eq!(notify.notify_type, NotifyType::Sign);
+        assert_eq!(notify.status, AgreementStatus::Normal);
+        assert_eq!(
+            notify.external_agreement_no.as_deref(),
+            Some("202401010001")
+        );
+
+        let body = body.replace("NORMAL", "STOP");
+        assert!(verify_notify(&signer, &body).is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use crate::error::AlipayResult;
 use openssl::{
+    base64,
     hash::{hash, MessageDigest},
     nid::Nid,
     x509::{X509NameEntries, X509},
@@ -25,20 +26,26 @@ pub(crate) fn get_cert_sn_from_content(content: &[u8]) -> AlipayResult<String> {
     let data = issuer + &serial_number;
     Ok(hex::encode(hash(MessageDigest::md5(), data.as_ref())?))
 }
+// 从证书中获取公钥，base64编码的DER，和支付宝公钥的格式相同
+pub(crate) fn get_public_key_from_content(content: &[u8]) -> AlipayResult<String> {
+    let der = X509::from_pem(content)?.public_key()?.public_key_to_der()?;
+    Ok(base64::encode_block(&der))
+}
 // 提取根证书序列号
 pub(crate) fn get_root_cert_sn_from_content(cert_content: &str) -> AlipayResult<String> {
-    let alipay_root_cert_sn: String = cert_content
+    let mut cert_sns = Vec::new();
+    // 最后一个证书之后可能还有换行，不是证书的内容返回错误
+    for cert in cert_content
         .split_inclusive("-----END CERTIFICATE-----")
-        .filter(|cert| {
-            let ssl = X509::from_pem(cert.as_ref()).unwrap();
-            let algorithm = ssl.signature_algorithm().object().nid();
-            algorithm == Nid::SHA256WITHRSAENCRYPTION || algorithm == Nid::SHA1WITHRSAENCRYPTION
-        })
-        .filter_map(|cert| get_cert_sn_from_content(cert.as_ref()).ok())
-        .collect::<Vec<String>>()
-        .join("_");
-
-    Ok(alipay_root_cert_sn)
+        .filter(|cert| !cert.trim().is_empty())
+    {
+        let ssl = X509::from_pem(cert.as_ref())?;
+        let algorithm = ssl.signature_algorithm().object().nid();
+        if algorithm == Nid::SHA256WITHRSAENCRYPTION || algorithm == Nid::SHA1WITHRSAENCRYPTION {
+            cert_sns.push(get_cert_sn_from_content(cert.as_ref())?);
+        }
+    }
+    Ok(cert_sns.join("_"))
 }
 pub(crate) fn get_root_cert_sn(cert_path: &str) -> AlipayResult<String> {
     let cert_content = fs::read_to_string(cert_path)?;
@@ -48,7 +55,7 @@ pub(crate) fn get_root_cert_sn(cert_path: &str) -> AlipayResult<String> {
 fn iter2string(iter: X509NameEntries) -> AlipayResult<String> {
     let mut string: String = String::from("");
     for value in iter {
-        let data = value.data().as_utf8()?.to_string();
+        let data = value.data().to_string()?;
         let key = value.object().nid().short_name()?.to_owned();
         string.insert_str(0, &(key + "=" + &data + ","));
     }
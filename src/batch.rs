@@ -0,0 +1,270 @@
+use crate::{
+    error::{AlipayError, AlipayResult},
+    rate_limit::RateLimiter,
+    response::Response,
+    retry::IDEMPOTENT_KEYS,
+    AlipayParams, Cli, RateLimit,
+};
+use std::{fmt, sync::Mutex, thread};
+
+/// 批量请求的并发数和速率
+///
+/// ```rust
+/// // 最多4个请求同时进行，每秒最多发出20个请求
+/// let options = alipay_rs::BatchOptions::new(4).rate_limit(20);
+/// ```
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    concurrency: usize,
+    rate_limit: Option<u32>,
+}
+
+impl BatchOptions {
+    /// concurrency: 同时进行的请求数，最小为1
+    pub fn new(concurrency: usize) -> Self {
+        BatchOptions {
+            concurrency: concurrency.max(1),
+            rate_limit: None,
+        }
+    }
+    /// 每秒最多发出的请求数，为0时不限制
+    pub fn rate_limit(mut self, per_second: u32) -> Self {
+        self.rate_limit = if per_second == 0 {
+            None
+        } else {
+            Some(per_second)
+        };
+        self
+    }
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        BatchOptions::new(8)
+    }
+}
+
+/// 批量请求中的一个请求的结果
+pub struct BatchItem {
+    pub method: String,
+    /// 请求中的商户单号（out_biz_no、out_trade_no或者out_request_no），用于重试失败的请求
+    pub out_no: Option<String>,
+    pub result: AlipayResult<Response>,
+}
+
+impl BatchItem {
+    /// 请求成功并且网关返回的code为10000
+    pub fn is_success(&self) -> bool {
+        match &self.result {
+            Ok(response) => response
+                .gateway_code()
+                .is_some_and(|(code, _)| code == "10000"),
+            Err(_) => false,
+        }
+    }
+}
+
+/// 失败的请求
+#[derive(Debug, Clone)]
+pub struct BatchFailure {
+    /// 在请求列表中的位置
+    pub index: usize,
+    pub method: String,
+    pub out_no: Option<String>,
+    /// 网关返回的code和sub_code，请求没有发出或者没有响应时为None
+    pub code: Option<(String, String)>,
+    pub message: String,
+}
+
+/// 批量请求的结果，顺序和请求的顺序一致
+pub struct BatchReport {
+    pub items: Vec<BatchItem>,
+}
+
+impl BatchReport {
+    pub fn is_success(&self) -> bool {
+        self.items.iter().all(|item| item.is_success())
+    }
+    pub fn succeeded(&self) -> usize {
+        self.items.iter().filter(|item| item.is_success()).count()
+    }
+    pub fn failures(&self) -> Vec<BatchFailure> {
+        self.items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| !item.is_success())
+            .map(|(index, item)| {
+                let (code, message) = match &item.result {
+                    Ok(response) => (response.gateway_code(), response.body().to_owned()),
+                    Err(err) => (None, err.to_string()),
+                };
+                BatchFailure {
+                    index,
+                    method: item.method.clone(),
+                    out_no: item.out_no.clone(),
+                    code,
+                    message,
+                }
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let succeeded = self.succeeded();
+        write!(
+            f,
+            "{} succeeded, {} failed",
+            succeeded,
+            self.items.len() - succeeded
+        )
+    }
+}
+
+pub(crate) fn run<C, I, S, T>(client: &C, requests: I, options: &BatchOptions) -> BatchReport
+where
+    C: Cli + Sync,
+    I: IntoIterator<Item = (S, T)>,
+    S: Into<String> + Send,
+    T: AlipayParams + Send,
+{
+    let requests: Vec<_> = requests
+        .into_iter()
+        .map(|(method, params)| (method.into(), params))
+        .enumerate()
+        .collect();
+    let total = requests.len();
+    let queue = Mutex::new(requests.into_iter());
+    // 令牌桶容量为1，请求按照固定间隔发出
+    let limiter = options
+        .rate_limit
+        .map(|per_second| RateLimiter::new(RateLimit::new(per_second).burst(1)));
+
+    let mut items: Vec<(usize, BatchItem)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..options.concurrency.min(total))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let next = queue.lock().unwrap_or_else(|e| e.into_inner()).next();
+                        let Some((index, (method, params))) = next else {
+                            break;
+                        };
+                        let item = match limiter.as_ref().map(|limiter| limiter.acquire(&method)) {
+                            Some(Err(err)) => BatchItem {
+                                method,
+                                out_no: None,
+                                result: Err(err),
+                            },
+                            _ => execute(client, method, params),
+                        };
+                        done.push((index, item));
+                    }
+                    done
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|err| std::panic::resume_unwind(err))
+            })
+            .collect()
+    });
+    items.sort_by_key(|(index, _)| *index);
+    BatchReport {
+        items: items.into_iter().map(|(_, item)| item).collect(),
+    }
+}
+
+fn execute<C, T>(client: &C, method: String, params: T) -> BatchItem
+where
+    C: Cli + Sync,
+    T: AlipayParams + Send,
+{
+    if let Err(err) = params.validate() {
+        return BatchItem {
+            method,
+            out_no: None,
+            result: Err(AlipayError::from(err)),
+        };
+    }
+    let params = params.to_alipay_value();
+    let out_no = IDEMPOTENT_KEYS
+        .iter()
+        .find_map(|key| params.get(key)?.as_str().map(|v| v.to_owned()));
+    let result = client.sync_post(method.as_str(), params);
+    BatchItem {
+        method,
+        out_no,
+        result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_util::{client, serve_biz},
+        trade::PayRequest,
+    };
+    use std::time::{Duration, Instant};
+
+    const PAY: &str = "alipay.trade.pay";
+
+    fn request(out_trade_no: &str) -> (&'static str, PayRequest) {
+        (
+            PAY,
+            PayRequest::new(
+                out_trade_no,
+                "28763443825664394",
+                "iphone",
+                "88.88".parse().unwrap(),
+            ),
+        )
+    }
+
+    #[test]
+    fn keeps_request_order_and_reports_failures() {
+        let (url, handle) = serve_biz(&[
+            (PAY, r#"{"code":"10000","msg":"Success"}"#),
+            (
+                PAY,
+                r#"{"code":"40004","msg":"Business Failed","sub_code":"ACQ.TRADE_HAS_SUCCESS","sub_msg":"交易已被支付"}"#,
+            ),
+        ]);
+        let requests = vec![request("1"), request(""), request("2")];
+        let report = client(&url).batch(requests, BatchOptions::new(1));
+        handle.join().unwrap();
+
+        assert_eq!(report.items.len(), 3);
+        assert_eq!(report.succeeded(), 1);
+        assert_eq!(report.to_string(), "1 succeeded, 2 failed");
+        let failures = report.failures();
+        // 没有商户单号的请求没有发出
+        assert_eq!(failures[0].index, 1);
+        assert_eq!(failures[0].code, None);
+        assert_eq!(failures[1].index, 2);
+        assert_eq!(failures[1].out_no.as_deref(), Some("2"));
+        assert_eq!(
+            failures[1].code,
+            Some(("40004".to_owned(), "ACQ.TRADE_HAS_SUCCESS".to_owned()))
+        );
+    }
+
+    #[test]
+    fn rate_limit_paces_requests() {
+        let success = r#"{"code":"10000","msg":"Success"}"#;
+        let (url, handle) = serve_biz(&[(PAY, success), (PAY, success), (PAY, success)]);
+        let requests = vec![request("1"), request("2"), request("3")];
+        let start = Instant::now();
+        let report = client(&url).batch(requests, BatchOptions::new(3).rate_limit(20));
+        handle.join().unwrap();
+        assert!(report.is_success());
+        // 第一个请求立即发出，之后每50ms一个
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+}
@@ -1,24 +1,27 @@
 use crate::{
-    app_cert_client, client_builder::ClientBuilder, error::AlipayResult, response::Response,
-    util::datetime, AlipayParams, BoxFuture, Cli, ClientWithParams, Sign,
+    app_cert_client, batch,
+    client_builder::ClientBuilder,
+    error::AlipayResult,
+    interceptor::AlipayRequest,
+    response::Response,
+    signer::SharedSigner,
+    transport::Transport,
+    upload,
+    util::{self, datetime},
+    AlipayParams, BatchOptions, BatchReport, BoxFuture, Cli, ClientConfig, ClientWithParams,
+    RsaSigner, SecretString, Sign, UploadForm,
 };
 use futures::FutureExt;
-use openssl::{
-    base64,
-    hash::MessageDigest,
-    pkey::{PKey, Private, Public},
-    rsa::Rsa,
-    sign::{Signer, Verifier},
-};
-use serde_json::Value;
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt, sync::Arc};
+use ureq::Request;
 
-#[derive(Debug)]
+#[derive(Clone)]
 pub struct Client {
-    public_key: String,
-    private_key: String,
+    signer: SharedSigner,
     request_params: HashMap<String, String>,
     sandbox: bool,
+    transport: Transport,
+    aes_key: Option<SecretString>,
 }
 
 impl Client {
@@ -55,13 +58,47 @@ impl Client {
             params.insert("alipay_root_cert_sn".to_owned(), alipay_root_cert_sn);
         }
         Self {
-            public_key: public_key.into(),
-            private_key: private_key.into(),
+            signer: Arc::new(RsaSigner::new(
+                SecretString::new(private_key),
+                &public_key.into(),
+            )),
             request_params: params,
             sandbox,
+            transport: Transport::default(),
+            aes_key: None,
         }
     }
 
+    pub(crate) fn with_transport(mut self, transport: Transport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    pub(crate) fn with_signer(mut self, signer: SharedSigner) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    pub(crate) fn with_aes_key(mut self, aes_key: Option<SecretString>) -> Self {
+        self.aes_key = aes_key;
+        self
+    }
+
+    /// 从环境变量创建客户端，支持的环境变量见ClientConfig::from_env
+    ///
+    /// ```rust,no_run
+    /// // ALIPAY_APP_ID=20210xxxxxxxxxxx ALIPAY_PRIVATE_KEY_PATH=/run/secrets/alipay_private_key
+    /// let client = alipay_rs::Client::from_env().unwrap();
+    /// ```
+    pub fn from_env() -> AlipayResult<Client> {
+        ClientConfig::from_env()?.build()
+    }
+
+    /// 接口内容加密密钥，expose后可以用于open::mini::decrypt_data
+    pub fn aes_key(&self) -> Option<&SecretString> {
+        self.aes_key.as_ref()
+    }
+
     /// app_id: 可在支付宝控制台 -> 我的应用 中查看
     /// public_key_path: 支付宝开放平台开发助手生成的应用公钥钥文件
     /// private_key_path: 支付宝开放平台开发助手生成的应用私钥文件
@@ -99,13 +136,13 @@ impl Client {
         )
     }
 
-    /// ```rust
+    /// ```rust,no_run
     /// let client = alipay_rs::Client::builder()
     /// .app_id("2021002199679230")
-    /// .public_key(include_str!("../公钥.txt"))
-    /// .private_key(include_str!("../私钥.txt"))
-    /// .app_cert_sn(include_str!("../appCertPublicKey_2021002199679230.crt"))
-    /// .alipay_root_cert_sn(include_str!("../alipayRootCert.crt"))
+    /// .public_key(&std::fs::read_to_string("公钥.txt").unwrap())
+    /// .private_key(&std::fs::read_to_string("私钥.txt").unwrap())
+    /// .app_cert_sn(&std::fs::read_to_string("appCertPublicKey_2021002199679230.crt").unwrap())
+    /// .alipay_root_cert_sn(&std::fs::read_to_string("alipayRootCert.crt").unwrap())
     /// .finish();
     /// ```
     pub fn builder<'a>() -> ClientBuilder<'a> {
@@ -114,9 +151,13 @@ impl Client {
 
     /// 设置/添加公共参数
     ///
+    /// 参数会先经过校验，校验失败时返回错误。
     ///
     /// Example:
-    /// ```rust
+    /// ```rust,no_run
+    /// # use alipay_rs::AlipayParams;
+    /// # use std::collections::HashMap;
+    /// # fn main() -> alipay_rs::error::AlipayResult<()> {
     /// #[derive(AlipayParams)]
     /// struct PublicParams {
     ///     app_id: String,
@@ -125,42 +166,66 @@ impl Client {
     ///     version: String,
     /// }
     ///
-    /// ......
+    /// // ......
     ///
+    /// # let client = alipay_rs::Client::builder().finish();
     ///     let public_params = PublicParams {
     ///         app_id: "20210xxxxxxxxxxx".to_owned(),
     ///         charset: "utf-8".to_owned(),
     ///         sign_type: "RSA2".to_owned(),
     ///         version: "1.0".to_owned(),
     ///     };
+    ///     client.set_public_params(public_params)?;
     ///
     ///     // 也可以通过vec, hashmap, array, tuple来设置公共参数
-    ///     let public_params = ("app_id", "20210xxxxxxxxxxx");
-    ///     let public_params = [("image_type", "png"), ("image_name", "test")];
-    ///     let public_params = vec![("image_type", "png"), ("image_name", "test")];
-    ///     let public_params = HashMap::from([("image_type", "png"), ("image_name", "test")]);
-    ///
-    ///     client.set_public_params(public_params);
+    ///     client.set_public_params(("app_id", "20210xxxxxxxxxxx"))?;
+    ///     client.set_public_params([("image_type", "png"), ("image_name", "test")])?;
+    ///     client.set_public_params(vec![("image_type", "png"), ("image_name", "test")])?;
+    ///     client.set_public_params(HashMap::from([("image_type", "png"), ("image_name", "test")]))?;
+    /// # Ok(())
+    /// # }
     /// ```
-    pub fn set_public_params<T>(&self, args: T) -> ClientWithParams
+    pub fn set_public_params<T>(&self, args: T) -> AlipayResult<ClientWithParams>
     where
         T: AlipayParams,
     {
-        let params = args.to_alipay_value().to_json_value();
-        let mut other_params = HashMap::new();
-
-        if let Value::Object(v) = params {
-            for (key, val) in v {
-                other_params.insert(key, val);
-            }
-        }
-        ClientWithParams::new(
-            self.public_key.clone(),
-            self.private_key.clone(),
+        let other_params = util::public_params(args)?.into_iter().collect();
+        Ok(ClientWithParams::new(
+            self.signer.clone(),
             self.request_params.clone(),
             other_params,
             self.sandbox,
-        )
+            self.transport.clone(),
+        ))
+    }
+
+    /// 批量请求，阻塞当前线程直到所有请求完成
+    ///
+    /// 请求在options.concurrency个线程中执行，结果的顺序和requests的顺序一致。
+    /// 某个请求失败不会影响其他请求，可以通过BatchReport::failures中的out_no重试失败的请求。
+    /// 在异步运行时中需要放到阻塞线程中执行，比如tokio的`spawn_blocking`。
+    ///
+    /// ```rust,no_run
+    /// use alipay_rs::BatchOptions;
+    ///
+    /// # fn payroll(client: &alipay_rs::Client, transfers: Vec<serde_json::Value>) {
+    /// let requests = transfers
+    ///     .into_iter()
+    ///     .map(|transfer| ("alipay.fund.trans.uni.transfer", transfer));
+    /// let report = client.batch(requests, BatchOptions::new(8).rate_limit(50));
+    /// println!("{}", report);
+    /// for failure in report.failures() {
+    ///     println!("{:?} {:?} {}", failure.out_no, failure.code, failure.message);
+    /// }
+    /// # }
+    /// ```
+    pub fn batch<I, S, T>(&self, requests: I, options: BatchOptions) -> BatchReport
+    where
+        I: IntoIterator<Item = (S, T)>,
+        S: Into<String> + Send,
+        T: AlipayParams + Send,
+    {
+        batch::run(self, requests, &options)
     }
 
     fn alipay_post<S: Into<String>>(
@@ -168,26 +233,60 @@ impl Client {
         method: S,
         biz_content: Option<String>,
     ) -> AlipayResult<Response> {
-        let url = if !self.sandbox {
-            "https://openapi.alipay.com/gateway.do"
-        } else {
-            "https://openapi.alipaydev.com/gateway.do"
-        };
-        let params = self.build_params(method.into(), biz_content)?;
-        let res = ureq::post(url)
-            .set(
-                "Content-Type",
-                "application/x-www-form-urlencoded;charset=utf-8",
+        let (request, http_request, body) = self.form_request(method.into(), biz_content)?;
+        self.transport.send(
+            request.method(),
+            request.get("biz_content"),
+            &http_request,
+            body.as_bytes(),
+        )
+    }
+    /// 与alipay_post相同，重试前的等待不会阻塞线程
+    async fn alipay_post_async<S: Into<String>>(
+        &self,
+        method: S,
+        biz_content: Option<String>,
+    ) -> AlipayResult<Response> {
+        let (request, http_request, body) = self.form_request(method.into(), biz_content)?;
+        self.transport
+            .send_async(
+                request.method(),
+                request.get("biz_content"),
+                &http_request,
+                body.as_bytes(),
             )
-            .send_string(&params)?;
-
-        Ok(Response::new(res))
+            .await
+    }
+    /// 签名并生成表单请求，返回签名后的参数、http请求和请求体
+    fn form_request(
+        &self,
+        method: String,
+        biz_content: Option<String>,
+    ) -> AlipayResult<(AlipayRequest, Request, String)> {
+        let request = self.create_params(method, biz_content, Vec::new())?;
+        let body = serde_urlencoded::to_string(&request.params)?;
+        let http_request = self.transport.post(&request.url).set(
+            "Content-Type",
+            "application/x-www-form-urlencoded;charset=utf-8",
+        );
+        Ok((request, http_request, body))
     }
+    /// 生成跳转页面需要的参数，不发送请求
+    fn url_request<T: AlipayParams>(
+        &self,
+        method: String,
+        biz_content: T,
+    ) -> AlipayResult<AlipayRequest> {
+        let biz_content = util::biz_content(biz_content)?;
+        self.create_params(method, biz_content, Vec::new())
+    }
+    /// text_params是需要参与签名的其他参数，比如文件上传时表单中的文本字段
     fn create_params(
         &self,
         method: String,
         biz_content: Option<String>,
-    ) -> AlipayResult<Vec<(String, String)>> {
+        text_params: Vec<(String, String)>,
+    ) -> AlipayResult<AlipayRequest> {
         let request_params_len = self.request_params.len();
 
         let now = datetime()?;
@@ -195,7 +294,7 @@ impl Client {
         let mut params: Vec<(String, String)> = Vec::with_capacity(request_params_len + 3);
 
         params.push(("timestamp".to_string(), now));
-        params.push(("method".to_string(), method));
+        params.push(("method".to_string(), method.clone()));
 
         for (key, val) in self.request_params.iter() {
             params.push((key.to_string(), val.to_string()));
@@ -204,10 +303,19 @@ impl Client {
         if let Some(content) = biz_content {
             params.push(("biz_content".to_string(), content));
         }
+        params.extend(text_params);
+
+        let url = if !self.sandbox {
+            "https://openapi.alipay.com/gateway.do"
+        } else {
+            "https://openapi.alipaydev.com/gateway.do"
+        };
+        let mut request = AlipayRequest::new(method, url.to_owned(), params);
+        self.transport.before_sign(&mut request)?;
 
-        params.sort_by(|a, b| a.0.cmp(&b.0));
+        request.params.sort_by(|a, b| a.0.cmp(&b.0));
         let mut temp = String::new();
-        for (key, val) in params.iter() {
+        for (key, val) in request.params.iter() {
             temp.push_str(key);
             temp.push('=');
             temp.push_str(val);
@@ -216,42 +324,30 @@ impl Client {
         temp.pop();
 
         let sign = self.sign(&temp)?;
-        params.push(("sign".to_owned(), sign));
-        Ok(params)
-    }
-    fn build_params(&self, method: String, biz_content: Option<String>) -> AlipayResult<String> {
-        let params = self.create_params(method, biz_content)?;
-        Ok(serde_urlencoded::to_string(params)?)
-    }
-
-    fn get_private_key(&self) -> AlipayResult<PKey<Private>> {
-        let cert_content = base64::decode_block(self.private_key.as_str())?;
-        let rsa = Rsa::private_key_from_der(&cert_content)?;
-
-        Ok(PKey::from_rsa(rsa)?)
+        request.params.push(("sign".to_owned(), sign));
+        self.transport.after_sign(&request)?;
+        Ok(request)
     }
-    fn get_public_key(&self) -> AlipayResult<PKey<Public>> {
-        let cert_content = base64::decode_block(self.public_key.as_str())?;
-        let rsa = Rsa::public_key_from_der(&cert_content)?;
+}
 
-        Ok(PKey::from_rsa(rsa)?)
+/// 不输出签名实现和加密密钥
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Client")
+            .field("app_id", &self.request_params.get("app_id"))
+            .field("aes_key", &self.aes_key)
+            .field("sandbox", &self.sandbox)
+            .field("transport", &self.transport)
+            .finish()
     }
 }
 
 impl Sign for Client {
     fn sign(&self, params: &str) -> AlipayResult<String> {
-        let private_key = self.get_private_key()?;
-        let mut signer = Signer::new(MessageDigest::sha256(), &private_key)?;
-        signer.update(params.as_bytes())?;
-        let sign = base64::encode_block(signer.sign_to_vec()?.as_ref());
-        Ok(sign)
+        self.signer.sign(params)
     }
     fn verify(&self, source: &str, signature: &str) -> AlipayResult<bool> {
-        let public_key = self.get_public_key()?;
-        let sign = base64::decode_block(signature)?;
-        let mut verifier = Verifier::new(MessageDigest::sha256(), &public_key)?;
-        verifier.update(source.as_bytes())?;
-        Ok(verifier.verify(sign.as_slice())?)
+        self.signer.verify(source, signature)
     }
 }
 
@@ -261,13 +357,17 @@ impl Cli for Client {
     /// 支付宝的官方接口都可以使用此函数访问
     ///
     /// Example:
-    /// ```rust
+    /// ```rust,no_run
+    /// # use alipay_rs::Cli;
+    /// # use std::collections::HashMap;
+    /// # async fn transfer(transfer: serde_json::Value) {
     ///    let client = alipay_rs::Client::new(
     ///         "20210xxxxxxxxxxx",
-    ///         include_str!("../公钥.txt"),
-    ///         include_str!("../私钥.txt"),
-    ///         Some(include_str!("../appCertPublicKey_20210xxxxxxxxxxx.crt")),
-    ///         Some(include_str!("../alipayRootCert.crt"))
+    ///         "MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8A...",
+    ///         "MIIEvQIBADANBgkqhkiG9w0BAQEFAASC...",
+    ///         Some("-----BEGIN CERTIFICATE-----..."),
+    ///         Some("-----BEGIN CERTIFICATE-----..."),
+    ///         false,
     ///     );
     ///     let data:serde_json::Value = client
     ///         .post("alipay.fund.trans.uni.transfer", transfer)
@@ -279,13 +379,18 @@ impl Cli for Client {
     ///     let params = [("image_type", "png"), ("image_name", "test")];
     ///     let params = vec![("image_type", "png"), ("image_name", "test")];
     ///     let params = HashMap::from([("image_type", "png"), ("image_name", "test")]);
+    /// # }
     /// ```
     fn post<'a, S, T>(&'a self, method: S, biz_content: T) -> BoxFuture<'a, AlipayResult<Response>>
     where
         S: Into<String> + Send + 'a,
         T: AlipayParams + Send + 'a,
     {
-        async move { self.sync_post::<'a, S, T>(method, biz_content) }.boxed()
+        async move {
+            let biz_content = util::biz_content(biz_content)?;
+            self.alipay_post_async(method, biz_content).await
+        }
+        .boxed()
     }
     /// 没有参数的异步请求
     /// 此函数后期考虑放弃，请调用post函数。
@@ -294,7 +399,7 @@ impl Cli for Client {
     where
         S: Into<String> + Send + 'a,
     {
-        async move { self.alipay_post::<S>(method, None) }.boxed()
+        async move { self.alipay_post_async(method, None).await }.boxed()
     }
     /// 同步请求
     fn sync_post<'a, S, T>(&'a self, method: S, biz_content: T) -> AlipayResult<Response>
@@ -302,15 +407,8 @@ impl Cli for Client {
         S: Into<String> + Send + 'a,
         T: AlipayParams + Send + 'a,
     {
-        let biz_content = biz_content.to_alipay_value();
-        if biz_content.is_null() {
-            self.alipay_post::<S>(method, None)
-        } else {
-            self.alipay_post::<S>(
-                method,
-                Some(serde_json::to_string(&biz_content.to_json_value())?),
-            )
-        }
+        let biz_content = util::biz_content(biz_content)?;
+        self.alipay_post(method, biz_content)
     }
 
     /// 文件上传
@@ -319,7 +417,9 @@ impl Cli for Client {
     /// file_name: 文件名
     /// file_content: 文件内容
     ///
-    /// ```rust
+    /// ```rust,no_run
+    /// # use alipay_rs::{AlipayParams, MutCli};
+    /// # async fn upload(client: &alipay_rs::Client) {
     /// #[derive(AlipayParams)]
     /// struct Image {
     ///     image_type: String,
@@ -330,10 +430,10 @@ impl Cli for Client {
     ///     image_type: "png".to_owned(),
     ///     image_name: "test".to_owned(),
     /// };
-    /// let client = ...;
-    /// let mut client_with_params = client.set_public_params(image);
+    /// let mut client_with_params = client.set_public_params(image).unwrap();
     /// let data:serde_json::Value = client_with_params.post_file("alipay.offline.material.image.upload", "image_content", "test.png", file.as_ref()).await.unwrap().into_json().unwrap();
     /// println!("{:?}", data);
+    /// # }
     /// ```
     fn post_file<'a, S>(
         &'a self,
@@ -344,26 +444,31 @@ impl Cli for Client {
     ) -> BoxFuture<'a, AlipayResult<Response>>
     where
         S: Into<String> + Send + 'a,
+    {
+        let mut form = UploadForm::new();
+        form.bytes(key, file_name, file_content, None);
+        self.post_multipart(method, (), form)
+    }
+    /// 上传多个文件，params作为表单中的文本字段，和公共参数一起签名
+    ///
+    /// 参数的值是字符串时直接使用，其他类型转换为json字符串。
+    /// 限流和重试前的等待不会阻塞线程，但是和post一样，http请求本身（包括读取reader和文件）
+    /// 在当前线程中同步执行，上传大文件时可以放到阻塞线程中执行。
+    fn post_multipart<'a, S, T>(
+        &'a self,
+        method: S,
+        params: T,
+        form: UploadForm<'a>,
+    ) -> BoxFuture<'a, AlipayResult<Response>>
+    where
+        S: Into<String> + Send + 'a,
+        T: AlipayParams + Send + 'a,
     {
         async move {
-            let mut multi = multipart::client::lazy::Multipart::new();
-            multi.add_stream(key, file_content, Some(file_name), None);
-            let mdata = multi.prepare()?;
-            let mut url = if !self.sandbox {
-                "https://openapi.alipay.com/gateway.do".to_owned()
-            } else {
-                "https://openapi.alipaydev.com/gateway.do".to_owned()
-            };
-            let params = self.build_params(method.into(), None)?;
-            url.push('?');
-            url.push_str(params.as_str());
-            let res = ureq::post(url.as_str())
-                .set(
-                    "Content-Type",
-                    &format!("multipart/form-data; boundary={}", mdata.boundary()),
-                )
-                .send(mdata)?;
-            Ok(Response::new(res))
+            let text_params = upload::text_params(params)?;
+            let text_keys: Vec<String> = text_params.iter().map(|(key, _)| key.clone()).collect();
+            let request = self.create_params(method.into(), None, text_params)?;
+            upload::send(&self.transport, request, &text_keys, form).await
         }
         .boxed()
     }
@@ -376,21 +481,108 @@ impl Cli for Client {
         S: Into<String> + Send + 'a,
         T: AlipayParams + Send + 'a,
     {
-        let biz_content = biz_content.to_alipay_value();
-        let params = if biz_content.is_null() {
-            self.create_params(method.into(), None)?
-        } else {
-            self.create_params(
-                method.into(),
-                Some(serde_json::to_string(&biz_content.to_json_value())?),
-            )?
+        Ok(self.url_request(method.into(), biz_content)?.params)
+    }
+    fn generate_url<'a, S, T>(&'a self, method: S, biz_content: T) -> AlipayResult<String>
+    where
+        S: Into<String> + Send + 'a,
+        T: AlipayParams + Send + 'a,
+    {
+        let request = self.url_request(method.into(), biz_content)?;
+        Ok(format!(
+            "{}?{}",
+            request.url,
+            serde_urlencoded::to_string(&request.params)?
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::AlipayResult,
+        test_util::{rsa_keys, AES_KEY},
+        CallRecord,
+    };
+    use futures::executor::block_on;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    #[derive(AlipayParams)]
+    struct Refund {
+        #[alipay(required)]
+        out_trade_no: String,
+        #[alipay(pattern = "^[0-9a-zA-Z_]+$")]
+        out_request_no: String,
+    }
+
+    /// 记录签名次数
+    struct CountingSigner(Arc<AtomicUsize>);
+
+    impl Sign for CountingSigner {
+        fn sign(&self, _params: &str) -> AlipayResult<String> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok("stub".to_owned())
+        }
+        fn verify(&self, _source: &str, _signature: &str) -> AlipayResult<bool> {
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn invalid_params_are_rejected_before_signing() {
+        let signed = Arc::new(AtomicUsize::new(0));
+        let sent = Arc::new(AtomicUsize::new(0));
+        let counter = sent.clone();
+        let client = Client::builder()
+            .app_id("2021000000000000")
+            .signer(CountingSigner(signed.clone()))
+            .metrics(move |_: &CallRecord| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .finish();
+
+        let refund = Refund {
+            out_trade_no: "6823789339978248".to_owned(),
+            out_request_no: "HZ01-RF001".to_owned(),
         };
-        // let url = if !self.sandbox {
-        //     "https://openapi.alipay.com/gateway.do".to_owned()
-        // } else {
-        //     "https://openapi.alipaydev.com/gateway.do".to_owned()
-        // };
-        // params.push(("url".to_owned(), url));
-        Ok(params)
+        let err = block_on(client.post("alipay.trade.refund", refund))
+            .err()
+            .unwrap();
+        let validation = err.validation_error().unwrap();
+        assert_eq!(validation.field, "out_request_no");
+
+        let refund = Refund {
+            out_trade_no: String::new(),
+            out_request_no: "HZ01RF001".to_owned(),
+        };
+        let err = client
+            .sync_post("alipay.trade.refund", refund)
+            .err()
+            .unwrap();
+        assert_eq!(err.validation_error().unwrap().field, "out_trade_no");
+
+        assert_eq!(signed.load(Ordering::SeqCst), 0);
+        assert_eq!(sent.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn debug_output_hides_keys() {
+        let (private_key, public_key) = rsa_keys();
+        let client = Client::builder()
+            .app_id("2021000000000000")
+            .private_key(&private_key)
+            .public_key(&public_key)
+            .aes_key(AES_KEY)
+            .finish();
+        for debug in [format!("{:?}", client), format!("{:#?}", client)] {
+            assert!(debug.contains("2021000000000000"));
+            assert!(!debug.contains(&private_key), "{}", debug);
+            assert!(!debug.contains(&private_key[private_key.len() - 32..]));
+            assert!(!debug.contains(AES_KEY), "{}", debug);
+        }
     }
 }
@@ -1,46 +1,51 @@
 use crate::{
-    error::AlipayResult, response::Response, util::datetime, AlipayParams, BoxFuture, MutCli, Sign,
+    error::AlipayResult,
+    interceptor::AlipayRequest,
+    response::Response,
+    signer::SharedSigner,
+    transport::Transport,
+    upload,
+    util::{self, datetime},
+    AlipayParams, BoxFuture, MutCli, Sign, UploadForm,
 };
 use futures::FutureExt;
-use openssl::{
-    base64,
-    hash::MessageDigest,
-    pkey::{PKey, Private, Public},
-    rsa::Rsa,
-    sign::{Signer, Verifier},
-};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
+use ureq::Request;
 
 pub struct ClientWithParams {
-    public_key: String,
-    private_key: String,
+    signer: SharedSigner,
     request_params: HashMap<String, String>,
     other_params: HashMap<String, Value>,
     sandbox: bool,
+    transport: Transport,
 }
 
 impl ClientWithParams {
     pub(crate) fn new(
-        public_key: String,
-        private_key: String,
+        signer: SharedSigner,
         request_params: HashMap<String, String>,
         other_params: HashMap<String, Value>,
         sandbox: bool,
+        transport: Transport,
     ) -> Self {
         Self {
-            public_key,
-            private_key,
+            signer,
             request_params,
             other_params,
             sandbox,
+            transport,
         }
     }
     /// 设置/添加公共参数
     ///
+    /// 参数会先经过校验，校验失败时返回错误，已经设置的公共参数不变。
     ///
     /// Example:
-    /// ```rust
+    /// ```rust,no_run
+    /// # use alipay_rs::AlipayParams;
+    /// # use std::collections::HashMap;
+    /// # fn main() -> alipay_rs::error::AlipayResult<()> {
     /// #[derive(AlipayParams)]
     /// struct PublicParams {
     ///     app_id: String,
@@ -49,34 +54,31 @@ impl ClientWithParams {
     ///     version: String,
     /// }
     ///
-    /// ......
+    /// // ......
     ///
+    /// # let mut client = alipay_rs::Client::builder().finish().set_public_params(())?;
     ///     let public_params = PublicParams {
     ///         app_id: "20210xxxxxxxxxxx".to_owned(),
     ///         charset: "utf-8".to_owned(),
     ///         sign_type: "RSA2".to_owned(),
     ///         version: "1.0".to_owned(),
     ///     };
+    ///     client.set_public_params(public_params)?;
     ///
     ///     // 也可以通过vec, hashmap, array, tuple来设置公共参数
-    ///     let public_params = ("app_id", "20210xxxxxxxxxxx");
-    ///     let public_params = [("image_type", "png"), ("image_name", "test")];
-    ///     let public_params = vec![("image_type", "png"), ("image_name", "test")];
-    ///     let public_params = HashMap::from([("image_type", "png"), ("image_name", "test")]);
-    ///
-    ///     client.set_public_params(public_params);
+    ///     client.set_public_params(("app_id", "20210xxxxxxxxxxx"))?;
+    ///     client.set_public_params([("image_type", "png"), ("image_name", "test")])?;
+    ///     client.set_public_params(vec![("image_type", "png"), ("image_name", "test")])?;
+    ///     client.set_public_params(HashMap::from([("image_type", "png"), ("image_name", "test")]))?;
+    /// # Ok(())
+    /// # }
     /// ```
-    pub fn set_public_params<T>(&mut self, args: T) -> &mut Self
+    pub fn set_public_params<T>(&mut self, args: T) -> AlipayResult<&mut Self>
     where
         T: AlipayParams,
     {
-        let params = args.to_alipay_value().to_json_value();
-        if let Value::Object(v) = params {
-            for (key, val) in v {
-                self.other_params.insert(key, val);
-            }
-        }
-        self
+        self.other_params.extend(util::public_params(args)?);
+        Ok(self)
     }
 
     fn alipay_post<S: Into<String>>(
@@ -84,27 +86,61 @@ impl ClientWithParams {
         method: S,
         biz_content: Option<String>,
     ) -> AlipayResult<Response> {
-        let url = if !self.sandbox {
-            "https://openapi.alipay.com/gateway.do"
-        } else {
-            "https://openapi.alipaydev.com/gateway.do"
-        };
-        let params = self.build_params(method.into(), biz_content)?;
-        let res = ureq::post(url)
-            .set(
-                "Content-Type",
-                "application/x-www-form-urlencoded;charset=utf-8",
+        let (request, http_request, body) = self.form_request(method.into(), biz_content)?;
+        self.transport.send(
+            request.method(),
+            request.get("biz_content"),
+            &http_request,
+            body.as_bytes(),
+        )
+    }
+    /// 与alipay_post相同，重试前的等待不会阻塞线程
+    async fn alipay_post_async<S: Into<String>>(
+        &mut self,
+        method: S,
+        biz_content: Option<String>,
+    ) -> AlipayResult<Response> {
+        let (request, http_request, body) = self.form_request(method.into(), biz_content)?;
+        self.transport
+            .send_async(
+                request.method(),
+                request.get("biz_content"),
+                &http_request,
+                body.as_bytes(),
             )
-            .send_string(&params)?;
-
-        Ok(Response::new(res))
+            .await
+    }
+    /// 签名并生成表单请求，返回签名后的参数、http请求和请求体
+    fn form_request(
+        &mut self,
+        method: String,
+        biz_content: Option<String>,
+    ) -> AlipayResult<(AlipayRequest, Request, String)> {
+        let request = self.create_params(method, biz_content, Vec::new())?;
+        let body = serde_urlencoded::to_string(&request.params)?;
+        let http_request = self.transport.post(&request.url).set(
+            "Content-Type",
+            "application/x-www-form-urlencoded;charset=utf-8",
+        );
+        Ok((request, http_request, body))
     }
 
+    /// 生成跳转页面需要的参数，不发送请求
+    fn url_request<T: AlipayParams>(
+        &mut self,
+        method: String,
+        biz_content: T,
+    ) -> AlipayResult<AlipayRequest> {
+        let biz_content = util::biz_content(biz_content)?;
+        self.create_params(method, biz_content, Vec::new())
+    }
+    /// text_params是需要参与签名的其他参数，比如文件上传时表单中的文本字段
     fn create_params(
         &mut self,
         method: String,
         biz_content: Option<String>,
-    ) -> AlipayResult<Vec<(String, String)>> {
+        text_params: Vec<(String, String)>,
+    ) -> AlipayResult<AlipayRequest> {
         let now = datetime()?;
 
         let request_params_len = self.request_params.len();
@@ -113,13 +149,14 @@ impl ClientWithParams {
             Vec::with_capacity(request_params_len + other_params_len + 3);
 
         params.push(("timestamp".to_string(), now));
-        params.push(("method".to_string(), method));
+        params.push(("method".to_string(), method.clone()));
         if let Some(biz_content) = biz_content {
             params.push(("biz_content".to_string(), biz_content));
         }
+        params.extend(text_params);
 
         for (key, val) in self.request_params.iter() {
-            if self.other_params.get(key).is_none() {
+            if !self.other_params.contains_key(key) {
                 params.push((key.to_string(), val.to_string()));
             }
         }
@@ -134,9 +171,17 @@ impl ClientWithParams {
         }
         self.other_params.clear();
 
-        params.sort_by(|a, b| a.0.cmp(&b.0));
+        let url = if !self.sandbox {
+            "https://openapi.alipay.com/gateway.do"
+        } else {
+            "https://openapi.alipaydev.com/gateway.do"
+        };
+        let mut request = AlipayRequest::new(method, url.to_owned(), params);
+        self.transport.before_sign(&mut request)?;
+
+        request.params.sort_by(|a, b| a.0.cmp(&b.0));
         let mut temp = String::new();
-        for (key, val) in params.iter() {
+        for (key, val) in request.params.iter() {
             temp.push_str(key);
             temp.push('=');
             temp.push_str(val);
@@ -145,29 +190,9 @@ impl ClientWithParams {
         temp.pop();
 
         let sign = self.sign(&temp)?;
-        params.push(("sign".to_owned(), sign));
-        Ok(params)
-    }
-
-    fn build_params(
-        &mut self,
-        method: String,
-        biz_content: Option<String>,
-    ) -> AlipayResult<String> {
-        let params = self.create_params(method, biz_content)?;
-        Ok(serde_urlencoded::to_string(params)?)
-    }
-    fn get_private_key(&self) -> AlipayResult<PKey<Private>> {
-        let cert_content = base64::decode_block(self.private_key.as_str())?;
-        let rsa = Rsa::private_key_from_der(cert_content.as_slice())?;
-
-        Ok(PKey::from_rsa(rsa)?)
-    }
-    fn get_public_key(&self) -> AlipayResult<PKey<Public>> {
-        let cert_content = base64::decode_block(self.public_key.as_str())?;
-        let rsa = Rsa::public_key_from_der(cert_content.as_slice())?;
-
-        Ok(PKey::from_rsa(rsa)?)
+        request.params.push(("sign".to_owned(), sign));
+        self.transport.after_sign(&request)?;
+        Ok(request)
     }
 }
 
@@ -177,14 +202,19 @@ impl MutCli for ClientWithParams {
     /// 支付宝的官方接口都可以使用此函数访问
     ///
     /// Example:
-    /// ```rust
+    /// ```rust,no_run
+    /// # use alipay_rs::MutCli;
+    /// # use std::collections::HashMap;
+    /// # async fn transfer(transfer: serde_json::Value) {
     ///    let client = alipay_rs::Client::new(
     ///         "20210xxxxxxxxxxx",
-    ///         include_str!("../公钥.txt"),
-    ///         include_str!("../私钥.txt"),
-    ///         Some(include_str!("../appCertPublicKey_20210xxxxxxxxxxx.crt")),
-    ///         Some(include_str!("../alipayRootCert.crt"))
+    ///         "MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8A...",
+    ///         "MIIEvQIBADANBgkqhkiG9w0BAQEFAASC...",
+    ///         Some("-----BEGIN CERTIFICATE-----..."),
+    ///         Some("-----BEGIN CERTIFICATE-----..."),
+    ///         false,
     ///     );
+    /// #   let mut client = client.set_public_params(()).unwrap();
     ///     let data:serde_json::Value = client
     ///         .post("alipay.fund.trans.uni.transfer", transfer)
     ///         .await.unwrap().into_json().unwrap();
@@ -195,6 +225,7 @@ impl MutCli for ClientWithParams {
     ///     let params = [("image_type", "png"), ("image_name", "test")];
     ///     let params = vec![("image_type", "png"), ("image_name", "test")];
     ///     let params = HashMap::from([("image_type", "png"), ("image_name", "test")]);
+    /// # }
     /// ```
     fn post<'a, S, T>(
         &'a mut self,
@@ -205,7 +236,11 @@ impl MutCli for ClientWithParams {
         S: Into<String> + Send + 'a,
         T: AlipayParams + Send + 'a,
     {
-        async move { self.sync_post(method, biz_content) }.boxed()
+        async move {
+            let biz_content = util::biz_content(biz_content)?;
+            self.alipay_post_async(method, biz_content).await
+        }
+        .boxed()
     }
     /// 没有参数的异步请求
     /// 此函数后期考虑放弃，请调用post函数。
@@ -214,7 +249,7 @@ impl MutCli for ClientWithParams {
     where
         S: Into<String> + Send + 'a,
     {
-        async move { self.alipay_post(method, None) }.boxed()
+        async move { self.alipay_post_async(method, None).await }.boxed()
     }
     /// 同步请求
     fn sync_post<'a, S, T>(&'a mut self, method: S, biz_content: T) -> AlipayResult<Response>
@@ -222,15 +257,8 @@ impl MutCli for ClientWithParams {
         S: Into<String> + Send + 'a,
         T: AlipayParams + Send + 'a,
     {
-        let biz_content = biz_content.to_alipay_value();
-        if biz_content.is_null() {
-            self.alipay_post::<S>(method, None)
-        } else {
-            self.alipay_post::<S>(
-                method,
-                Some(serde_json::to_string(&biz_content.to_json_value())?),
-            )
-        }
+        let biz_content = util::biz_content(biz_content)?;
+        self.alipay_post(method, biz_content)
     }
 
     /// 文件上传
@@ -239,7 +267,9 @@ impl MutCli for ClientWithParams {
     /// file_name: 文件名
     /// file_content: 文件内容
     ///
-    /// ```rust
+    /// ```rust,no_run
+    /// # use alipay_rs::{AlipayParams, MutCli};
+    /// # async fn upload(client: &alipay_rs::Client) {
     /// #[derive(AlipayParams)]
     /// struct Image {
     ///     image_type: String,
@@ -250,10 +280,10 @@ impl MutCli for ClientWithParams {
     ///     image_type: "png".to_owned(),
     ///     image_name: "test".to_owned(),
     /// };
-    /// let client = ...;
-    /// let mut client_with_params = client.set_public_params(image);
+    /// let mut client_with_params = client.set_public_params(image).unwrap();
     /// let data:serde_json::Value = client_with_params.post_file("alipay.offline.material.image.upload", "image_content", "test.png", file.as_ref()).await.unwrap().into_json().unwrap();
     /// println!("{:?}", data);
+    /// # }
     /// ```
     fn post_file<'a, S>(
         &'a mut self,
@@ -264,26 +294,31 @@ impl MutCli for ClientWithParams {
     ) -> BoxFuture<'a, AlipayResult<Response>>
     where
         S: Into<String> + Send + 'a,
+    {
+        let mut form = UploadForm::new();
+        form.bytes(key, file_name, file_content, None);
+        self.post_multipart(method, (), form)
+    }
+    /// 上传多个文件，params作为表单中的文本字段，和公共参数一起签名
+    ///
+    /// 参数的值是字符串时直接使用，其他类型转换为json字符串。
+    /// 限流和重试前的等待不会阻塞线程，但是和post一样，http请求本身（包括读取reader和文件）
+    /// 在当前线程中同步执行，上传大文件时可以放到阻塞线程中执行。
+    fn post_multipart<'a, S, T>(
+        &'a mut self,
+        method: S,
+        params: T,
+        form: UploadForm<'a>,
+    ) -> BoxFuture<'a, AlipayResult<Response>>
+    where
+        S: Into<String> + Send + 'a,
+        T: AlipayParams + Send + 'a,
     {
         async move {
-            let mut multi = multipart::client::lazy::Multipart::new();
-            multi.add_stream(key, file_content, Some(file_name), None);
-            let mdata = multi.prepare()?;
-            let mut url = if !self.sandbox {
-                "https://openapi.alipay.com/gateway.do".to_owned()
-            } else {
-                "https://openapi.alipaydev.com/gateway.do".to_owned()
-            };
-            let params = self.build_params(method.into(), None)?;
-            url.push('?');
-            url.push_str(params.as_str());
-            let res = ureq::post(url.as_str())
-                .set(
-                    "Content-Type",
-                    &format!("multipart/form-data; boundary={}", mdata.boundary()),
-                )
-                .send(mdata)?;
-            Ok(Response::new(res))
+            let text_params = upload::text_params(params)?;
+            let text_keys: Vec<String> = text_params.iter().map(|(key, _)| key.clone()).collect();
+            let request = self.create_params(method.into(), None, text_params)?;
+            upload::send(&self.transport, request, &text_keys, form).await
         }
         .boxed()
     }
@@ -296,38 +331,134 @@ impl MutCli for ClientWithParams {
         S: Into<String> + Send + 'a,
         T: AlipayParams + Send + 'a,
     {
-        let biz_content = biz_content.to_alipay_value();
-        let params = if biz_content.is_null() {
-            self.create_params(method.into(), None)?
-        } else {
-            self.create_params(
-                method.into(),
-                Some(serde_json::to_string(&biz_content.to_json_value())?),
-            )?
-        };
-        // let url = if !self.sandbox {
-        //     "https://openapi.alipay.com/gateway.do".to_owned()
-        // } else {
-        //     "https://openapi.alipaydev.com/gateway.do".to_owned()
-        // };
-        // params.push(("url".to_owned(), url));
-        Ok(params)
+        Ok(self.url_request(method.into(), biz_content)?.params)
+    }
+    fn generate_url<'a, S, T>(&'a mut self, method: S, biz_content: T) -> AlipayResult<String>
+    where
+        S: Into<String> + Send + 'a,
+        T: AlipayParams + Send + 'a,
+    {
+        let request = self.url_request(method.into(), biz_content)?;
+        Ok(format!(
+            "{}?{}",
+            request.url,
+            serde_urlencoded::to_string(&request.params)?
+        ))
+    }
+}
+
+/// 不输出签名实现，公共参数只输出参数名
+impl fmt::Debug for ClientWithParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientWithParams")
+            .field("app_id", &self.request_params.get("app_id"))
+            .field(
+                "other_params",
+                &self.other_params.keys().collect::<Vec<_>>(),
+            )
+            .field("sandbox", &self.sandbox)
+            .field("transport", &self.transport)
+            .finish()
     }
 }
 
 impl Sign for ClientWithParams {
     fn sign(&self, params: &str) -> AlipayResult<String> {
-        let private_key = self.get_private_key()?;
-        let mut signer = Signer::new(MessageDigest::sha256(), &private_key)?;
-        signer.update(params.as_bytes())?;
-        let sign = base64::encode_block(signer.sign_to_vec()?.as_ref());
-        Ok(sign)
+        self.signer.sign(params)
     }
     fn verify(&self, source: &str, signature: &str) -> AlipayResult<bool> {
-        let public_key = self.get_public_key()?;
-        let sign = base64::decode_block(signature)?;
-        let mut verifier = Verifier::new(MessageDigest::sha256(), &public_key)?;
-        verifier.update(source.as_bytes())?;
-        Ok(verifier.verify(sign.as_slice())?)
+        self.signer.verify(source, signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        test_util::{rsa_keys, AES_KEY},
+        AlipayParams, Client, SerdeParams,
+    };
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[derive(AlipayParams)]
+    struct PublicParams {
+        #[alipay(required)]
+        notify_url: String,
+    }
+
+    fn client() -> Client {
+        Client::builder().app_id("2021000000000000").finish()
+    }
+
+    #[test]
+    fn set_public_params() {
+        let mut client = client()
+            .set_public_params(("notify_url", "https://example.com/notify"))
+            .unwrap();
+        client
+            .set_public_params([("image_type", "png"), ("image_name", "test")])
+            .unwrap();
+        assert_eq!(
+            client.other_params["notify_url"],
+            json!("https://example.com/notify")
+        );
+        assert_eq!(client.other_params["image_type"], json!("png"));
+        assert_eq!(client.other_params.len(), 3);
+    }
+
+    #[test]
+    fn invalid_public_params_are_rejected() {
+        let err = client()
+            .set_public_params(PublicParams {
+                notify_url: String::new(),
+            })
+            .unwrap_err();
+        assert!(err.validation_error().is_some());
+
+        let mut client = client()
+            .set_public_params(("notify_url", "https://example.com/notify"))
+            .unwrap();
+        let err = client
+            .set_public_params(PublicParams {
+                notify_url: String::new(),
+            })
+            .unwrap_err();
+        assert!(err.validation_error().is_some());
+        // 校验失败时已经设置的参数不变
+        assert_eq!(
+            client.other_params["notify_url"],
+            json!("https://example.com/notify")
+        );
+    }
+
+    #[test]
+    fn unserializable_public_params_are_errors() {
+        // json对象的key只能是字符串
+        let params = SerdeParams(HashMap::from([((1, 2), "a")]));
+        assert!(client().set_public_params(params).is_err());
+
+        let mut client = client().set_public_params(()).unwrap();
+        let params = SerdeParams(HashMap::from([((1, 2), "a")]));
+        assert!(client.set_public_params(params).is_err());
+        assert!(client.other_params.is_empty());
+    }
+
+    #[test]
+    fn debug_output_hides_keys() {
+        let (private_key, public_key) = rsa_keys();
+        let client = Client::builder()
+            .app_id("2021000000000000")
+            .private_key(&private_key)
+            .public_key(&public_key)
+            .aes_key(AES_KEY)
+            .finish()
+            .set_public_params(("notify_url", "https://example.com/notify"))
+            .unwrap();
+        for debug in [format!("{:?}", client), format!("{:#?}", client)] {
+            assert!(debug.contains("notify_url"));
+            assert!(!debug.contains(&private_key), "{}", debug);
+            assert!(!debug.contains(&private_key[private_key.len() - 32..]));
+            assert!(!debug.contains(AES_KEY), "{}", debug);
+        }
     }
 }
@@ -0,0 +1,663 @@
+use crate::{
+    app_cert_client,
+    error::{AlipayError, AlipayResult},
+    Client, SecretString,
+};
+use openssl::{base64, rsa::Rsa};
+use serde::Deserialize;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use zeroize::Zeroizing;
+
+/// 网关环境
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Environment {
+    #[default]
+    Production,
+    Sandbox,
+}
+
+/// 客户端配置，可以从环境变量或者配置文件中读取，密钥不需要编译进程序
+///
+/// 私钥和支付宝公钥可以直接配置内容，也可以配置文件路径，二者选一。
+/// 公钥证书模式下需要配置应用公钥证书和支付宝根证书，验签使用的支付宝公钥
+/// 可以从支付宝公钥证书中读取（alipay_public_cert_path），这时不需要再配置支付宝公钥。
+/// json格式始终可用，toml和yaml格式需要启用对应的feature。
+/// 私钥和加密密钥保存为SecretString，Debug输出中不包含密钥内容。
+///
+/// ```rust,no_run
+/// # fn main() -> alipay_rs::error::AlipayResult<()> {
+/// let config: alipay_rs::ClientConfig = alipay_rs::ClientConfig::from_json(
+///     r#"{
+///         "app_id": "20210xxxxxxxxxxx",
+///         "private_key_path": "/run/secrets/alipay_private_key",
+///         "public_key_path": "/run/secrets/alipay_public_key",
+///         "environment": "sandbox",
+///         "timeout_ms": 10000
+///     }"#,
+/// )?;
+/// let client = config.build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientConfig {
+    pub app_id: String,
+    /// 应用私钥，base64编码，不包含PEM的头尾
+    pub private_key: Option<SecretString>,
+    pub private_key_path: Option<PathBuf>,
+    /// 支付宝公钥，用于验签
+    pub public_key: Option<String>,
+    pub public_key_path: Option<PathBuf>,
+    /// 应用公钥证书，公钥证书模式下需要
+    pub app_cert_path: Option<PathBuf>,
+    /// 支付宝根证书，公钥证书模式下需要
+    pub alipay_root_cert_path: Option<PathBuf>,
+    /// 支付宝公钥证书，公钥证书模式下用于验签，和public_key、public_key_path三者选一
+    pub alipay_public_cert_path: Option<PathBuf>,
+    /// 目前只支持RSA2
+    pub sign_type: Option<String>,
+    pub environment: Environment,
+    pub timeout_ms: Option<u64>,
+    pub connect_timeout_ms: Option<u64>,
+    pub read_timeout_ms: Option<u64>,
+    /// 接口内容加密密钥，base64编码
+    pub aes_key: Option<SecretString>,
+}
+
+impl ClientConfig {
+    /// 从环境变量读取配置
+    ///
+    /// 支持的环境变量：ALIPAY_APP_ID、ALIPAY_PRIVATE_KEY、ALIPAY_PRIVATE_KEY_PATH、
+    /// ALIPAY_PUBLIC_KEY、ALIPAY_PUBLIC_KEY_PATH、ALIPAY_APP_CERT_PATH、
+    /// ALIPAY_ROOT_CERT_PATH、ALIPAY_PUBLIC_CERT_PATH、ALIPAY_SIGN_TYPE、ALIPAY_ENVIRONMENT（production或sandbox）、
+    /// ALIPAY_TIMEOUT_MS、ALIPAY_CONNECT_TIMEOUT_MS、ALIPAY_READ_TIMEOUT_MS、ALIPAY_AES_KEY
+    pub fn from_env() -> AlipayResult<Self> {
+        let environment = match var("ALIPAY_ENVIRONMENT").as_deref() {
+            None | Some("production") => Environment::Production,
+            Some("sandbox") => Environment::Sandbox,
+            Some(other) => {
+                return Err(AlipayError::new(format!(
+                    "config: invalid ALIPAY_ENVIRONMENT: {}",
+                    other
+                )))
+            }
+        };
+        Ok(ClientConfig {
+            app_id: var("ALIPAY_APP_ID").unwrap_or_default(),
+            private_key: var("ALIPAY_PRIVATE_KEY").map(SecretString::new),
+            private_key_path: var("ALIPAY_PRIVATE_KEY_PATH").map(PathBuf::from),
+            public_key: var("ALIPAY_PUBLIC_KEY"),
+            public_key_path: var("ALIPAY_PUBLIC_KEY_PATH").map(PathBuf::from),
+            app_cert_path: var("ALIPAY_APP_CERT_PATH").map(PathBuf::from),
+            alipay_root_cert_path: var("ALIPAY_ROOT_CERT_PATH").map(PathBuf::from),
+            alipay_public_cert_path: var("ALIPAY_PUBLIC_CERT_PATH").map(PathBuf::from),
+            sign_type: var("ALIPAY_SIGN_TYPE"),
+            environment,
+            timeout_ms: millis_var("ALIPAY_TIMEOUT_MS")?,
+            connect_timeout_ms: millis_var("ALIPAY_CONNECT_TIMEOUT_MS")?,
+            read_timeout_ms: millis_var("ALIPAY_READ_TIMEOUT_MS")?,
+            aes_key: var("ALIPAY_AES_KEY").map(SecretString::new),
+        })
+    }
+
+    pub fn from_json(content: &str) -> AlipayResult<Self> {
+        Ok(serde_json::from_str(content)?)
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml(content: &str) -> AlipayResult<Self> {
+        toml::from_str(content).map_err(|e| AlipayError::new(format!("config: {}", e)))
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(content: &str) -> AlipayResult<Self> {
+        serde_yaml::from_str(content).map_err(|e| AlipayError::new(format!("config: {}", e)))
+    }
+
+    /// 根据扩展名读取配置文件，支持json、toml和yaml（yml）
+    pub fn from_file<P: AsRef<Path>>(path: P) -> AlipayResult<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json(&content),
+            #[cfg(feature = "toml")]
+            Some("toml") => Self::from_toml(&content),
+            #[cfg(feature = "yaml")]
+            Some("yaml") | Some("yml") => Self::from_yaml(&content),
+            _ => Err(AlipayError::new(format!(
+                "config: unsupported config file: {}",
+                path.display()
+            ))),
+        }
+    }
+
+    /// 检查配置是否完整，不读取文件
+    pub fn validate(&self) -> AlipayResult<()> {
+        if self.app_id.trim().is_empty() {
+            return Err(AlipayError::new("config: app_id is required"));
+        }
+        match (&self.private_key, &self.private_key_path) {
+            (None, None) => {
+                return Err(AlipayError::new(
+                    "config: private_key or private_key_path is required",
+                ))
+            }
+            (Some(_), Some(_)) => {
+                return Err(AlipayError::new(
+                    "config: private_key and private_key_path are mutually exclusive",
+                ))
+            }
+            _ => {}
+        }
+        if self.public_key.is_some() && self.public_key_path.is_some() {
+            return Err(AlipayError::new(
+                "config: public_key and public_key_path are mutually exclusive",
+            ));
+        }
+        if self.app_cert_path.is_some() != self.alipay_root_cert_path.is_some() {
+            return Err(AlipayError::new(
+                "config: app_cert_path and alipay_root_cert_path must be set together",
+            ));
+        }
+        if self.alipay_public_cert_path.is_some() {
+            if self.app_cert_path.is_none() {
+                return Err(AlipayError::new(
+                    "config: alipay_public_cert_path requires app_cert_path and alipay_root_cert_path",
+                ));
+            }
+            if self.public_key.is_some() || self.public_key_path.is_some() {
+                return Err(AlipayError::new(
+                    "config: alipay_public_cert_path and public_key are mutually exclusive",
+                ));
+            }
+        }
+        if let Some(sign_type) = &self.sign_type {
+            if sign_type != "RSA2" {
+                return Err(AlipayError::new(format!(
+                    "config: unsupported sign_type: {}",
+                    sign_type
+                )));
+            }
+        }
+        if let Some(aes_key) = &self.aes_key {
+            let len = base64::decode_block(aes_key.expose())
+                .map(Zeroizing::new)
+                .map_err(|_| AlipayError::new("config: aes_key is not valid base64"))?
+                .len();
+            if ![16, 24, 32].contains(&len) {
+                return Err(AlipayError::new(format!(
+                    "config: invalid aes_key length: {}",
+                    len
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// 读取密钥和证书并创建客户端，密钥或者证书无效时返回错误
+    pub fn build(&self) -> AlipayResult<Client> {
+        self.validate()?;
+        let private_key = load_key(
+            self.private_key.as_ref().map(SecretString::expose),
+            &self.private_key_path,
+        )?
+        .unwrap_or_default();
+        let der = Zeroizing::new(base64::decode_block(private_key.expose())?);
+        Rsa::private_key_from_der(&der)
+            .map_err(|_| AlipayError::new("config: invalid private_key"))?;
+        let public_key = match read_cert(&self.alipay_public_cert_path)? {
+            Some(cert) => Some(SecretString::new(
+                app_cert_client::get_public_key_from_content(cert.as_bytes())
+                    .map_err(|_| AlipayError::new("config: invalid alipay public cert"))?,
+            )),
+            None => load_key(self.public_key.as_deref(), &self.public_key_path)?,
+        };
+        if let Some(public_key) = &public_key {
+            Rsa::public_key_from_der(&base64::decode_block(public_key.expose())?)
+                .map_err(|_| AlipayError::new("config: invalid public_key"))?;
+        }
+        let app_cert = read_cert(&self.app_cert_path)?;
+        if let Some(cert) = &app_cert {
+            app_cert_client::get_cert_sn_from_content(cert.as_bytes())
+                .map_err(|_| AlipayError::new("config: invalid app cert"))?;
+        }
+        let root_cert = read_cert(&self.alipay_root_cert_path)?;
+        if let Some(cert) = &root_cert {
+            app_cert_client::get_root_cert_sn_from_content(cert)
+                .map_err(|_| AlipayError::new("config: invalid alipay root cert"))?;
+        }
+
+        let mut builder = Client::builder();
+        builder
+            .app_id(&self.app_id)
+            .private_key(private_key.expose());
+        if let Some(public_key) = &public_key {
+            builder.public_key(public_key.expose());
+        }
+        if let (Some(app_cert), Some(root_cert)) = (&app_cert, &root_cert) {
+            builder.app_cert_sn(app_cert).alipay_root_cert_sn(root_cert);
+        }
+        if self.environment == Environment::Sandbox {
+            builder.sandbox();
+        }
+        if let Some(ms) = self.timeout_ms {
+            builder.timeout(Duration::from_millis(ms));
+        }
+        if let Some(ms) = self.connect_timeout_ms {
+            builder.connect_timeout(Duration::from_millis(ms));
+        }
+        if let Some(ms) = self.read_timeout_ms {
+            builder.read_timeout(Duration::from_millis(ms));
+        }
+        if let Some(aes_key) = &self.aes_key {
+            builder.aes_key(aes_key.expose());
+        }
+        Ok(builder.finish())
+    }
+}
+
+fn var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|val| !val.trim().is_empty())
+}
+
+fn millis_var(name: &str) -> AlipayResult<Option<u64>> {
+    var(name)
+        .map(|val| {
+            val.trim()
+                .parse()
+                .map_err(|_| AlipayError::new(format!("config: invalid {}: {}", name, val)))
+        })
+        .transpose()
+}
+
+/// 密钥文件中可能有换行，统一去掉空白
+fn load_key(content: Option<&str>, path: &Option<PathBuf>) -> AlipayResult<Option<SecretString>> {
+    let content = match (content, path) {
+        (Some(content), _) => content.split_whitespace().collect(),
+        (None, Some(path)) => {
+            let content = Zeroizing::new(fs::read_to_string(path)?);
+            content.split_whitespace().collect()
+        }
+        (None, None) => return Ok(None),
+    };
+    Ok(Some(SecretString::new::<String>(content)))
+}
+
+fn read_cert(path: &Option<PathBuf>) -> AlipayResult<Option<String>> {
+    path.as_ref()
+        .map(|path| Ok(fs::read_to_string(path)?))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util::AES_KEY, Cli, RsaSigner, Sign};
+    use openssl::{
+        asn1::Asn1Time,
+        bn::BigNum,
+        hash::MessageDigest,
+        pkey::{PKey, Private},
+        x509::{X509Builder, X509NameBuilder},
+    };
+
+    struct Keys {
+        private_key: String,
+        public_key: String,
+        pkey: PKey<Private>,
+    }
+
+    fn keys() -> Keys {
+        let rsa = Rsa::generate(2048).unwrap();
+        Keys {
+            private_key: base64::encode_block(&rsa.private_key_to_der().unwrap()),
+            public_key: base64::encode_block(&rsa.public_key_to_der().unwrap()),
+            pkey: PKey::from_rsa(rsa).unwrap(),
+        }
+    }
+
+    /// 自签名的证书，PEM格式
+    fn cert(key: &PKey<Private>, common_name: &str) -> String {
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", common_name).unwrap();
+        let name = name.build();
+        let mut builder = X509Builder::new().unwrap();
+        builder.set_version(2).unwrap();
+        let serial = BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap();
+        builder.set_serial_number(&serial).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        builder.sign(key, MessageDigest::sha256()).unwrap();
+        String::from_utf8(builder.build().to_pem().unwrap()).unwrap()
+    }
+
+    /// 测试用的临时目录，每个测试使用不同的名字
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("alipay-rs-config-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn config(keys: &Keys) -> ClientConfig {
+        ClientConfig {
+            app_id: "2021000000000000".to_owned(),
+            private_key: Some(SecretString::new(keys.private_key.as_str())),
+            public_key: Some(keys.public_key.clone()),
+            ..ClientConfig::default()
+        }
+    }
+
+    fn error(result: AlipayResult<impl std::fmt::Debug>) -> String {
+        result.unwrap_err().to_string()
+    }
+
+    #[test]
+    fn validate_requires_app_id_and_private_key() {
+        let keys = keys();
+        assert!(config(&keys).validate().is_ok());
+        let config = |f: fn(&mut ClientConfig)| {
+            let mut config = config(&keys);
+            f(&mut config);
+            config
+        };
+        assert!(error(config(|c| c.app_id = " ".to_owned()).validate())
+            .contains("config: app_id is required"));
+        assert!(error(config(|c| c.private_key = None).validate())
+            .contains("config: private_key or private_key_path is required"));
+    }
+
+    #[test]
+    fn validate_rejects_mutually_exclusive_settings() {
+        let keys = keys();
+        let mut both_private = config(&keys);
+        both_private.private_key_path = Some("private_key.txt".into());
+        assert!(error(both_private.validate())
+            .contains("private_key and private_key_path are mutually exclusive"));
+
+        let mut both_public = config(&keys);
+        both_public.public_key_path = Some("public_key.txt".into());
+        assert!(error(both_public.validate())
+            .contains("public_key and public_key_path are mutually exclusive"));
+
+        let mut app_cert_only = config(&keys);
+        app_cert_only.app_cert_path = Some("appCertPublicKey.crt".into());
+        assert!(error(app_cert_only.validate())
+            .contains("app_cert_path and alipay_root_cert_path must be set together"));
+
+        let mut public_cert_only = config(&keys);
+        public_cert_only.public_key = None;
+        public_cert_only.alipay_public_cert_path = Some("alipayCertPublicKey_RSA2.crt".into());
+        assert!(error(public_cert_only.validate())
+            .contains("alipay_public_cert_path requires app_cert_path and alipay_root_cert_path"));
+
+        let mut cert_and_public_key = config(&keys);
+        cert_and_public_key.app_cert_path = Some("appCertPublicKey.crt".into());
+        cert_and_public_key.alipay_root_cert_path = Some("alipayRootCert.crt".into());
+        cert_and_public_key.alipay_public_cert_path = Some("alipayCertPublicKey_RSA2.crt".into());
+        assert!(error(cert_and_public_key.validate())
+            .contains("alipay_public_cert_path and public_key are mutually exclusive"));
+    }
+
+    #[test]
+    fn validate_checks_sign_type_and_aes_key() {
+        let keys = keys();
+        let mut config = config(&keys);
+        config.sign_type = Some("RSA".to_owned());
+        assert!(error(config.validate()).contains("unsupported sign_type: RSA"));
+        config.sign_type = Some("RSA2".to_owned());
+
+        config.aes_key = Some(SecretString::new("not base64!"));
+        assert!(error(config.validate()).contains("aes_key is not valid base64"));
+        config.aes_key = Some(SecretString::new("AAAAAAAAAAA="));
+        assert!(error(config.validate()).contains("invalid aes_key length: 8"));
+        config.aes_key = Some(SecretString::new(AES_KEY));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn from_env_reads_variables() {
+        let vars = [
+            ("ALIPAY_APP_ID", "2021000000000000"),
+            ("ALIPAY_PRIVATE_KEY_PATH", "/run/secrets/alipay_private_key"),
+            ("ALIPAY_PUBLIC_KEY", "public"),
+            ("ALIPAY_APP_CERT_PATH", "appCertPublicKey.crt"),
+            ("ALIPAY_ROOT_CERT_PATH", "alipayRootCert.crt"),
+            ("ALIPAY_PUBLIC_CERT_PATH", "alipayCertPublicKey_RSA2.crt"),
+            ("ALIPAY_ENVIRONMENT", "sandbox"),
+            ("ALIPAY_TIMEOUT_MS", " 10000 "),
+            ("ALIPAY_AES_KEY", AES_KEY),
+            // 空值和没有设置一样
+            ("ALIPAY_PRIVATE_KEY", " "),
+        ];
+        for (name, val) in vars {
+            env::set_var(name, val);
+        }
+        let config = ClientConfig::from_env().unwrap();
+        assert_eq!(config.app_id, "2021000000000000");
+        assert!(config.private_key.is_none());
+        assert_eq!(
+            config.private_key_path,
+            Some("/run/secrets/alipay_private_key".into())
+        );
+        assert_eq!(config.public_key.as_deref(), Some("public"));
+        assert_eq!(config.app_cert_path, Some("appCertPublicKey.crt".into()));
+        assert_eq!(
+            config.alipay_root_cert_path,
+            Some("alipayRootCert.crt".into())
+        );
+        assert_eq!(
+            config.alipay_public_cert_path,
+            Some("alipayCertPublicKey_RSA2.crt".into())
+        );
+        assert_eq!(config.environment, Environment::Sandbox);
+        assert_eq!(config.timeout_ms, Some(10000));
+        assert_eq!(config.connect_timeout_ms, None);
+        assert_eq!(
+            config.aes_key.as_ref().map(SecretString::expose),
+            Some(AES_KEY)
+        );
+
+        env::set_var("ALIPAY_TIMEOUT_MS", "10s");
+        assert!(error(ClientConfig::from_env()).contains("invalid ALIPAY_TIMEOUT_MS: 10s"));
+        env::set_var("ALIPAY_TIMEOUT_MS", "10000");
+        env::set_var("ALIPAY_ENVIRONMENT", "staging");
+        assert!(error(ClientConfig::from_env()).contains("invalid ALIPAY_ENVIRONMENT: staging"));
+        for (name, _) in vars {
+            env::remove_var(name);
+        }
+    }
+
+    #[test]
+    fn from_json_rejects_unknown_fields() {
+        let config = ClientConfig::from_json(
+            r#"{"app_id":"2021000000000000","private_key":"key","environment":"sandbox","read_timeout_ms":500}"#,
+        )
+        .unwrap();
+        assert_eq!(config.app_id, "2021000000000000");
+        assert_eq!(
+            config.private_key.as_ref().map(SecretString::expose),
+            Some("key")
+        );
+        assert_eq!(config.environment, Environment::Sandbox);
+        assert_eq!(config.read_timeout_ms, Some(500));
+        assert!(ClientConfig::from_json(r#"{"app_id":"2021000000000000","appid":"x"}"#).is_err());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn from_toml() {
+        let config = ClientConfig::from_toml(
+            "app_id = \"2021000000000000\"\nprivate_key_path = \"key.txt\"\nenvironment = \"sandbox\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.app_id, "2021000000000000");
+        assert_eq!(config.private_key_path, Some("key.txt".into()));
+        assert_eq!(config.environment, Environment::Sandbox);
+        assert!(
+            error(ClientConfig::from_toml("appid = \"x\"")).starts_with("alipay error: config:")
+        );
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn from_yaml() {
+        let config = ClientConfig::from_yaml(
+            "app_id: \"2021000000000000\"\nprivate_key_path: key.txt\nenvironment: sandbox\n",
+        )
+        .unwrap();
+        assert_eq!(config.app_id, "2021000000000000");
+        assert_eq!(config.private_key_path, Some("key.txt".into()));
+        assert_eq!(config.environment, Environment::Sandbox);
+        assert!(error(ClientConfig::from_yaml("appid: x")).starts_with("alipay error: config:"));
+    }
+
+    #[test]
+    fn from_file_dispatches_by_extension() {
+        let dir = temp_dir("from_file");
+        let json = dir.join("alipay.json");
+        fs::write(&json, r#"{"app_id":"2021000000000000"}"#).unwrap();
+        assert_eq!(
+            ClientConfig::from_file(&json).unwrap().app_id,
+            "2021000000000000"
+        );
+
+        let toml = dir.join("alipay.toml");
+        fs::write(&toml, "app_id = \"2021000000000000\"").unwrap();
+        #[cfg(feature = "toml")]
+        assert_eq!(
+            ClientConfig::from_file(&toml).unwrap().app_id,
+            "2021000000000000"
+        );
+        #[cfg(not(feature = "toml"))]
+        assert!(error(ClientConfig::from_file(&toml)).contains("unsupported config file"));
+
+        let yaml = dir.join("alipay.yml");
+        fs::write(&yaml, "app_id: \"2021000000000000\"").unwrap();
+        #[cfg(feature = "yaml")]
+        assert_eq!(
+            ClientConfig::from_file(&yaml).unwrap().app_id,
+            "2021000000000000"
+        );
+        #[cfg(not(feature = "yaml"))]
+        assert!(error(ClientConfig::from_file(&yaml)).contains("unsupported config file"));
+
+        let ini = dir.join("alipay.ini");
+        fs::write(&ini, "app_id=2021000000000000").unwrap();
+        assert!(error(ClientConfig::from_file(&ini)).contains("unsupported config file"));
+        assert!(ClientConfig::from_file(dir.join("missing.json")).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn build_rejects_invalid_keys_and_certs() {
+        let keys = keys();
+        assert!(config(&keys).build().is_ok());
+
+        let mut invalid_private_key = config(&keys);
+        invalid_private_key.private_key = Some(SecretString::new(keys.public_key.as_str()));
+        assert!(error(invalid_private_key.build()).contains("config: invalid private_key"));
+
+        let mut invalid_public_key = config(&keys);
+        invalid_public_key.public_key = Some("AAAA".to_owned());
+        assert!(error(invalid_public_key.build()).contains("config: invalid public_key"));
+
+        let mut missing_key_file = config(&keys);
+        missing_key_file.private_key = None;
+        missing_key_file.private_key_path = Some(temp_dir("missing").join("private_key.txt"));
+        assert!(missing_key_file.build().is_err());
+
+        let dir = temp_dir("invalid_cert");
+        let not_a_cert = dir.join("appCertPublicKey.crt");
+        fs::write(&not_a_cert, "not a cert").unwrap();
+        let mut invalid_cert = config(&keys);
+        invalid_cert.app_cert_path = Some(not_a_cert.clone());
+        invalid_cert.alipay_root_cert_path = Some(not_a_cert);
+        assert!(error(invalid_cert.build()).contains("config: invalid app cert"));
+        let app_cert = dir.join("app.crt");
+        fs::write(&app_cert, cert(&keys.pkey, "app")).unwrap();
+        invalid_cert.app_cert_path = Some(app_cert);
+        assert!(error(invalid_cert.build()).contains("config: invalid alipay root cert"));
+        fs::remove_dir_all(dir).unwrap();
+
+        let mut invalid_validation = config(&keys);
+        invalid_validation.app_id = String::new();
+        assert!(error(invalid_validation.build()).contains("config: app_id is required"));
+    }
+
+    #[test]
+    fn cert_mode_from_file() {
+        let app = keys();
+        let alipay = keys();
+        let dir = temp_dir("cert_mode");
+        let app_cert = cert(&app.pkey, "app");
+        let root_cert = cert(&alipay.pkey, "Alipay Root");
+        let alipay_cert = cert(&alipay.pkey, "Alipay");
+        for (file, content) in [
+            ("private_key.txt", app.private_key.as_str()),
+            ("appCertPublicKey.crt", app_cert.as_str()),
+            ("alipayRootCert.crt", root_cert.as_str()),
+            ("alipayCertPublicKey_RSA2.crt", alipay_cert.as_str()),
+        ] {
+            fs::write(dir.join(file), content).unwrap();
+        }
+        let config_path = dir.join("alipay.json");
+        fs::write(
+            &config_path,
+            serde_json::json!({
+                "app_id": "2021000000000000",
+                "private_key_path": dir.join("private_key.txt"),
+                "app_cert_path": dir.join("appCertPublicKey.crt"),
+                "alipay_root_cert_path": dir.join("alipayRootCert.crt"),
+                "alipay_public_cert_path": dir.join("alipayCertPublicKey_RSA2.crt"),
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let client = ClientConfig::from_file(&config_path)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let params = client.generate_url_data("alipay.trade.query", ()).unwrap();
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, val)| val.clone())
+        };
+        assert_eq!(
+            param("app_cert_sn"),
+            Some(app_cert_client::get_cert_sn_from_content(app_cert.as_bytes()).unwrap())
+        );
+        assert_eq!(
+            param("alipay_root_cert_sn"),
+            Some(app_cert_client::get_root_cert_sn_from_content(&root_cert).unwrap())
+        );
+
+        // 支付宝的响应用支付宝公钥证书中的公钥验签
+        let alipay_signer = RsaSigner::new(alipay.private_key.as_str(), &alipay.public_key);
+        let sign = alipay_signer.sign("content").unwrap();
+        assert!(client.verify("content", &sign).unwrap());
+
+        let mut invalid = ClientConfig::from_file(&config_path).unwrap();
+        invalid.alipay_public_cert_path = Some(dir.join("appCertPublicKey.crt"));
+        let other = alipay_signer.sign("content").unwrap();
+        assert!(!invalid.build().unwrap().verify("content", &other).unwrap());
+        invalid.alipay_public_cert_path = Some(dir.join("private_key.txt"));
+        assert!(error(invalid.build()).contains("config: invalid alipay public cert"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
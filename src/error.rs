@@ -1,8 +1,10 @@
+use alipay_params::{MoneyError, ValidationError};
 use multipart::client::lazy::LazyIoError as MultipartLazyIoError;
 use openssl::error::Error as OpensslError;
 use openssl::error::ErrorStack as OpensslErrorStack;
 use openssl::ssl::Error as SslError;
 use serde_json::Error as SerdeJsonError;
+use serde_urlencoded::de::Error as SerdeUrlEncodeDeError;
 use serde_urlencoded::ser::Error as SerdeUrlEncodeSerError;
 use std::error::Error;
 use std::fmt;
@@ -14,17 +16,63 @@ use std::time::SystemTimeError;
 use ureq::Error as UreqError;
 
 #[derive(Debug)]
-pub struct AlipayError(String);
+pub struct AlipayError {
+    message: String,
+    validation: Option<ValidationError>,
+    biz: Option<Box<BizError>>,
+    /// 网络错误或者http错误状态，请求结果未知
+    transport: bool,
+}
 
 impl AlipayError {
     pub fn new<S: Into<String>>(message: S) -> Self {
-        AlipayError(message.into())
+        AlipayError {
+            message: message.into(),
+            validation: None,
+            biz: None,
+            transport: false,
+        }
+    }
+    /// 参数校验失败时返回具体的字段和规则
+    pub fn validation_error(&self) -> Option<&ValidationError> {
+        self.validation.as_ref()
+    }
+    /// 网关返回业务错误时返回错误码，可以根据sub_code处理
+    pub fn biz_error(&self) -> Option<&BizError> {
+        self.biz.as_deref()
     }
+    /// 是否是发送请求时的网络错误或者http错误状态
+    pub fn is_transport_error(&self) -> bool {
+        self.transport
+    }
+}
+
+/// 网关返回的业务错误，code不是10000
+///
+/// 错误码说明：<https://opendocs.alipay.com/common/02km9f>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BizError {
+    pub code: String,
+    pub msg: String,
+    pub sub_code: String,
+    pub sub_msg: String,
 }
 
+impl fmt::Display for BizError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} ({} {})",
+            self.code, self.msg, self.sub_code, self.sub_msg
+        )
+    }
+}
+
+impl Error for BizError {}
+
 impl fmt::Display for AlipayError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "alipay error: {}", self.0)
+        write!(f, "alipay error: {}", self.message)
     }
 }
 
@@ -53,7 +101,10 @@ impl From<SslError> for AlipayError {
 }
 impl From<UreqError> for AlipayError {
     fn from(error: UreqError) -> Self {
-        AlipayError::new(error.to_string())
+        AlipayError {
+            transport: true,
+            ..AlipayError::new(error.to_string())
+        }
     }
 }
 impl From<SerdeJsonError> for AlipayError {
@@ -66,6 +117,11 @@ impl From<SerdeUrlEncodeSerError> for AlipayError {
         AlipayError::new(error.to_string())
     }
 }
+impl From<SerdeUrlEncodeDeError> for AlipayError {
+    fn from(error: SerdeUrlEncodeDeError) -> Self {
+        AlipayError::new(error.to_string())
+    }
+}
 impl From<FromUtf8Error> for AlipayError {
     fn from(error: FromUtf8Error) -> Self {
         AlipayError::new(error.to_string())
@@ -81,6 +137,31 @@ impl From<SystemTimeError> for AlipayError {
         AlipayError::new(error.to_string())
     }
 }
+impl From<MoneyError> for AlipayError {
+    fn from(error: MoneyError) -> Self {
+        AlipayError::new(error.to_string())
+    }
+}
+impl From<ValidationError> for AlipayError {
+    fn from(error: ValidationError) -> Self {
+        AlipayError {
+            message: error.to_string(),
+            validation: Some(error),
+            biz: None,
+            transport: false,
+        }
+    }
+}
+impl From<BizError> for AlipayError {
+    fn from(error: BizError) -> Self {
+        AlipayError {
+            message: error.to_string(),
+            validation: None,
+            biz: Some(Box::new(error)),
+            transport: false,
+        }
+    }
+}
 impl<T> From<PoisonError<T>> for AlipayError {
     fn from(error: PoisonError<T>) -> Self {
         AlipayError::new(error.to_string())
@@ -0,0 +1,91 @@
+use crate::{
+    error::{AlipayError, AlipayResult},
+    response::Response,
+};
+
+/// 发往网关的请求
+///
+/// params是公共参数和biz_content组成的列表，签名前可以修改，签名后包含`sign`。
+#[derive(Debug, Clone)]
+pub struct AlipayRequest {
+    method: String,
+    /// 网关地址，可以改写成测试用的地址
+    pub url: String,
+    /// 请求参数
+    pub params: Vec<(String, String)>,
+}
+
+impl AlipayRequest {
+    pub(crate) fn new<S: Into<String>>(method: S, url: S, params: Vec<(String, String)>) -> Self {
+        AlipayRequest {
+            method: method.into(),
+            url: url.into(),
+            params,
+        }
+    }
+    /// 接口名称
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+    /// 获取参数值
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+    /// 设置参数，已经存在的参数会被覆盖
+    pub fn set<K: Into<String>, V: Into<String>>(&mut self, key: K, val: V) -> &mut Self {
+        let key = key.into();
+        let val = val.into();
+        match self.params.iter_mut().find(|(k, _)| *k == key) {
+            Some(param) => param.1 = val,
+            None => self.params.push((key, val)),
+        }
+        self
+    }
+    /// 删除参数
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let index = self.params.iter().position(|(k, _)| k == key)?;
+        Some(self.params.remove(index).1)
+    }
+}
+
+/// 请求拦截器
+///
+/// 所有请求（post、sync_post、post_file、generate_url_data）都会依次经过拦截器，
+/// 拦截器按照添加的顺序调用，任何一个返回错误都会中止请求。
+///
+/// ```rust
+/// use alipay_rs::{error::AlipayResult, AlipayRequest, Interceptor};
+///
+/// struct AppAuthToken(String);
+///
+/// impl Interceptor for AppAuthToken {
+///     fn before_sign(&self, request: &mut AlipayRequest) -> AlipayResult<()> {
+///         request.set("app_auth_token", self.0.as_str());
+///         Ok(())
+///     }
+/// }
+///
+/// let client = alipay_rs::Client::builder()
+///     .app_id("20210xxxxxxxxxxx")
+///     .interceptor(AppAuthToken("202xxxxxxxxx".to_owned()))
+///     .finish();
+/// ```
+pub trait Interceptor: Send + Sync {
+    /// 签名前调用，可以修改参数和网关地址
+    fn before_sign(&self, _request: &mut AlipayRequest) -> AlipayResult<()> {
+        Ok(())
+    }
+    /// 签名后调用，此时params已经包含sign，不应该再修改
+    fn after_sign(&self, _request: &AlipayRequest) -> AlipayResult<()> {
+        Ok(())
+    }
+    /// 收到响应后调用，generate_url_data不会发送请求，所以不会调用
+    fn on_response(&self, _method: &str, _response: &mut Response) -> AlipayResult<()> {
+        Ok(())
+    }
+    /// 请求失败时调用，包括网络错误、http错误状态和on_response返回的错误
+    fn on_error(&self, _method: &str, _error: &AlipayError) {}
+}
@@ -4,19 +4,19 @@
 //! ```toml
 //! [dependencies]
 //!
-//! alipay-rs = "0.4"
-//! alipay_params = "0.1"
+//! alipay-rs = "0.5"
+//! alipay_params = "0.2"
 //!
 //! ```
 //!
 //! # Example:
-//! ```rust
+//! ```rust,no_run
 //! // 默认的公共参数只包含了最基础的，如果需要增加公共参数，可用通过set_public_params函数实现
 //! // 默认的公共参数包含：app_id，charset，sign_type，format，version，method，timestamp，sign
 //! // 通过set_public_params设置公共参数，如果参数值为None会自动过滤，重复的参数后面的值会覆盖前面的值
 //! // 下面是单笔转账的几种示例
-//! use chrono::Local;
-//! use alipay_rs::AlipayParam;
+//! use alipay_rs::{AlipayParams, MutCli};
+//! use std::time::{SystemTime, UNIX_EPOCH};
 //!
 //! // 单笔转账接口需要的参数
 //! #[derive(AlipayParams, Debug)]
@@ -34,10 +34,30 @@
 //!     name: String,
 //! }
 //!
+//! fn timestamp() -> String {
+//!     SystemTime::now()
+//!         .duration_since(UNIX_EPOCH)
+//!         .unwrap()
+//!         .as_secs()
+//!         .to_string()
+//! }
+//!
+//! fn client() -> alipay_rs::Client {
+//!     alipay_rs::Client::builder()
+//!        .app_id("20210xxxxxxxxxxx")
+//!        .public_key("MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8A...")
+//!        .private_key("MIIEvQIBADANBgkqhkiG9w0BAQEFAASC...")
+//!        .app_cert_sn("-----BEGIN CERTIFICATE-----...")
+//!        .alipay_root_cert_sn("-----BEGIN CERTIFICATE-----...")
+//!        .finish()
+//! }
+//!
 //! // 通过post方法访问单笔转账接口
 //! async fn naive_fund_transfer() {
+//!     use alipay_rs::Cli;
+//!
 //!     let transfer = Transfer {
-//!         out_biz_no: format!("{}", Local::now().timestamp()),
+//!         out_biz_no: timestamp(),
 //!         trans_amount: String::from("0.1"),
 //!         product_code: String::from("TRANS_ACCOUNT_NO_PWD"),
 //!         biz_scene: String::from("DIRECT_TRANSFER"),
@@ -47,14 +67,7 @@
 //!             name: String::from("陈怀远"),
 //!         },
 //!     };
-//!     let client = alipay_rs::Client::builder()
-//!        .app_id("20210xxxxxxxxxxx")
-//!        .public_key(include_str!("../公钥.txt"))
-//!        .private_key(include_str!("../私钥.txt"))
-//!        .app_cert_sn(include_str!("../appCertPublicKey_20210xxxxxxxxxxx.crt"))
-//!        .alipay_root_cert_sn(include_str!("../alipayRootCert.crt"))
-//!        .finish();
-//!     let data:serde_json::Value = client
+//!     let data:serde_json::Value = client()
 //!         .post("alipay.fund.trans.uni.transfer", transfer)
 //!         .await.unwrap().into_json().unwrap();
 //!     println!("{:?}", data);
@@ -62,7 +75,7 @@
 //!
 //!
 //! // 公共参数
-//! #[derive(AlipayParam)]
+//! #[derive(AlipayParams)]
 //! struct PublicParams {
 //!     app_id: String,
 //!     method: Option<String>,
@@ -75,7 +88,7 @@
 //! // 修改公共参数来访问单笔转账接口
 //! async fn fund_transfer_from_public_params() {
 //!     let transfer = Transfer {
-//!         out_biz_no: format!("{}", Local::now().timestamp()),
+//!         out_biz_no: timestamp(),
 //!         trans_amount: String::from("0.1"),
 //!         product_code: String::from("TRANS_ACCOUNT_NO_PWD"),
 //!         biz_scene: String::from("DIRECT_TRANSFER"),
@@ -85,13 +98,6 @@
 //!             name: String::from("陈怀远"),
 //!         },
 //!     };
-//!     let client = alipay_rs::Client::builder()
-//!        .app_id("20210xxxxxxxxxxx")
-//!        .public_key(include_str!("../公钥.txt"))
-//!        .private_key(include_str!("../私钥.txt"))
-//!        .app_cert_sn(include_str!("../appCertPublicKey_20210xxxxxxxxxxx.crt"))
-//!        .alipay_root_cert_sn(include_str!("../alipayRootCert.crt"))
-//!        .finish();
 //!     let public_params = PublicParams {
 //!         app_id: "20210xxxxxxxxxxx".to_owned(),
 //!         method: None,
@@ -101,7 +107,7 @@
 //!         timestamp: None,
 //!         version: "1.0".to_owned(),
 //!     };
-//!     let mut client_with_params = client.set_public_params(public_params);
+//!     let mut client_with_params = client().set_public_params(public_params).unwrap();
 //!     let data:serde_json::Value = client_with_params
 //!         .post("alipay.fund.trans.uni.transfer", transfer)
 //!         .await.unwrap().into_json().unwrap();
@@ -112,32 +118,28 @@
 //! async fn image_upload() {
 //! let file = std::fs::read("./test.png").unwrap();
 //! let image = [("image_type", "png"), ("image_name", "test")];
-//! let config = alipay_rs::Config::builder()
-//!    .app_id("20210xxxxxxxxxxx")
-//!    .public_key(include_str!("../公钥.txt"))
-//!    .private_key(include_str!("../私钥.txt"))
-//!    .app_cert_sn(include_str!("../appCertPublicKey_20210xxxxxxxxxxx.crt"))
-//!    .alipay_root_cert_sn(include_str!("../alipayRootCert.crt"))
-//!    .finish();
-//! let client = config.get_client();
-//! let mut client_with_params = client.set_public_params(image);
+//! let mut client_with_params = client().set_public_params(image).unwrap();
 //!
 //! let data:serde_json::Value = client_with_params.post_file("alipay.offline.material.image.upload", "image_content", "test.png", file.as_ref()).await.unwrap().into_json().unwrap();
 //! println!("{:?}", data);
 //! }
-//! #[tokio::main]
-//! async fn main() {
-//!     // naive_fund_transfer().await;
-//!     // fund_transfer().await;
-//!     fund_transfer_from_public_params().await;
+//!
+//! fn main() {
+//!     // futures::executor::block_on(naive_fund_transfer());
+//!     // futures::executor::block_on(image_upload());
+//!     futures::executor::block_on(fund_transfer_from_public_params());
 //! }
 //! ```
 //! # Example2:
-//! ```rust
-//! use alipay_rs::AlipayParam;
-//! use chrono::Local;
+//! ```rust,no_run
+//! // 已经实现了serde::Serialize的类型可以通过SerdeParams包装后作为参数
+//! use alipay_rs::{Cli, SerdeParams};
 //! use serde::Serialize;
-//! use std::collections::HashMap;
+//! use std::{
+//!     sync::Arc,
+//!     thread,
+//!     time::{SystemTime, UNIX_EPOCH},
+//! };
 //!
 //! #[derive(Serialize, Debug)]
 //! struct Transfer {
@@ -171,14 +173,15 @@
 //!     };
 //!
 //!     let data:serde_json::Value = client
-//!         .post("alipay.open.mini.item.page.query", query)
+//!         .post("alipay.open.mini.item.page.query", SerdeParams(query))
 //!         .await.unwrap().into_json().unwrap();
 //!     println!("{:?}", data);
 //! }
 //!
 //! async fn ref_fund_transfer(client: &alipay_rs::Client) {
+//!     let out_biz_no = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
 //!     let transfer = Transfer {
-//!         out_biz_no: format!("{}", Local::now().timestamp()),
+//!         out_biz_no: out_biz_no.to_string(),
 //!         trans_amount: String::from("0.1"),
 //!         product_code: String::from("TRANS_ACCOUNT_NO_PWD"),
 //!         biz_scene: String::from("DIRECT_TRANSFER"),
@@ -189,52 +192,85 @@
 //!         },
 //!     };
 //!     let data:serde_json::Value = client
-//!         .post("alipay.fund.trans.uni.transfer", transfer)
+//!         .post("alipay.fund.trans.uni.transfer", SerdeParams(transfer))
 //!         .await.unwrap().into_json().unwrap();
 //!     println!("{:?}", data);
 //! }
-//! #[tokio::main]
-//! async fn main() {
+//!
+//! fn main() {
 //!
 //!     let client = alipay_rs::Client::builder()
 //!        .app_id("20210xxxxxxxxxxx")
-//!        .public_key(include_str!("../公钥.txt"))
-//!        .private_key(include_str!("../私钥.txt"))
-//!        .app_cert_sn(include_str!("../appCertPublicKey_20210xxxxxxxxxxx.crt"))
-//!        .alipay_root_cert_sn(include_str!("../alipayRootCert.crt"))
+//!        .public_key("MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8A...")
+//!        .private_key("MIIEvQIBADANBgkqhkiG9w0BAQEFAASC...")
+//!        .app_cert_sn("-----BEGIN CERTIFICATE-----...")
+//!        .alipay_root_cert_sn("-----BEGIN CERTIFICATE-----...")
 //!        .finish();
 //!
-//!     ref_query(&client).await;
-//!     ref_fund_transfer(&client).await;
+//!     futures::executor::block_on(ref_query(&client));
+//!     futures::executor::block_on(ref_fund_transfer(&client));
 //!
 //!     // 多线程调用
 //!     let cli = Arc::new(client);
 //!     let cli_clone = cli.clone();
-//!     tokio::spawn(async move {
-//!         ref_query(&cli_clone).await;
-//!     }).await.unwrap();
-//!     tokio::spawn(async move {
-//!         ref_fund_transfer(&cli.clone()).await;
-//!     }).await.unwrap();
+//!     let query = thread::spawn(move || {
+//!         futures::executor::block_on(ref_query(&cli_clone));
+//!     });
+//!     let transfer = thread::spawn(move || {
+//!         futures::executor::block_on(ref_fund_transfer(&cli));
+//!     });
+//!     query.join().unwrap();
+//!     transfer.join().unwrap();
 //! }
 //! ```
 
+pub mod agreement;
 mod app_cert_client;
+mod batch;
 mod client;
 mod client_builder;
 mod client_with_params;
+mod config;
+mod interceptor;
+mod metrics;
+pub mod notify;
+pub mod open;
+mod rate_limit;
 mod response;
+mod retry;
+pub mod royalty;
+mod secret;
+mod signer;
+#[cfg(test)]
+mod test_util;
+pub mod trade;
+mod transport;
+mod upload;
 
 mod util;
 
+pub use batch::{BatchFailure, BatchItem, BatchOptions, BatchReport};
 pub use client_builder::ClientBuilder;
 pub use client_with_params::ClientWithParams;
+pub use config::{ClientConfig, Environment};
+pub use interceptor::{AlipayRequest, Interceptor};
 pub mod error;
-pub use alipay_params::{AlipayParams, AlipayValue};
+pub use alipay_params::{
+    AlipayObject, AlipayParams, AlipayParamsRef, AlipayValue, Money, MoneyError, SerdeParams,
+    ValidationError, ValidationRule,
+};
 pub use client::Client;
+use error::AlipayError;
 use error::AlipayResult;
-use futures::future::BoxFuture;
+use futures::future::{self, BoxFuture};
+pub use metrics::{CallRecord, Metrics};
+pub use rate_limit::RateLimit;
 pub use response::Response;
+pub use retry::RetryPolicy;
+pub use secret::{SecretProvider, SecretString};
+pub use signer::RsaSigner;
+pub use upload::UploadForm;
+pub use ureq::{Agent, AgentBuilder, Proxy};
 
 pub trait Sign {
     fn sign(&self, params: &str) -> AlipayResult<String>;
@@ -265,6 +301,24 @@ pub trait Cli {
     ) -> BoxFuture<'a, AlipayResult<Response>>
     where
         S: Into<String> + Send + 'a;
+    /// 上传多个文件，params作为表单中的文本字段，和公共参数一起签名
+    ///
+    /// 默认实现返回错误，支持上传的客户端需要覆盖这个方法。
+    fn post_multipart<'a, S, T>(
+        &'a self,
+        method: S,
+        _params: T,
+        _form: UploadForm<'a>,
+    ) -> BoxFuture<'a, AlipayResult<Response>>
+    where
+        S: Into<String> + Send + 'a,
+        T: AlipayParams + Send + 'a,
+    {
+        Box::pin(future::ready(Err(not_implemented(
+            "post_multipart",
+            method.into(),
+        ))))
+    }
     fn generate_url_data<'a, S, T>(
         &'a self,
         method: S,
@@ -273,6 +327,16 @@ pub trait Cli {
     where
         S: Into<String> + Send + 'a,
         T: AlipayParams + Send + 'a;
+    /// 生成跳转到支付宝页面的完整url，比如alipay.user.agreement.page.sign
+    ///
+    /// 默认实现返回错误，需要生成url的客户端需要覆盖这个方法。
+    fn generate_url<'a, S, T>(&'a self, method: S, _biz_content: T) -> AlipayResult<String>
+    where
+        S: Into<String> + Send + 'a,
+        T: AlipayParams + Send + 'a,
+    {
+        Err(not_implemented("generate_url", method.into()))
+    }
 }
 
 pub trait MutCli {
@@ -303,6 +367,24 @@ pub trait MutCli {
     ) -> BoxFuture<'a, AlipayResult<Response>>
     where
         S: Into<String> + Send + 'a;
+    /// 上传多个文件，params作为表单中的文本字段，和公共参数一起签名
+    ///
+    /// 默认实现返回错误，支持上传的客户端需要覆盖这个方法。
+    fn post_multipart<'a, S, T>(
+        &'a mut self,
+        method: S,
+        _params: T,
+        _form: UploadForm<'a>,
+    ) -> BoxFuture<'a, AlipayResult<Response>>
+    where
+        S: Into<String> + Send + 'a,
+        T: AlipayParams + Send + 'a,
+    {
+        Box::pin(future::ready(Err(not_implemented(
+            "post_multipart",
+            method.into(),
+        ))))
+    }
     fn generate_url_data<'a, S, T>(
         &'a mut self,
         method: S,
@@ -311,4 +393,18 @@ pub trait MutCli {
     where
         S: Into<String> + Send + 'a,
         T: AlipayParams + Send + 'a;
+    /// 生成跳转到支付宝页面的完整url，比如alipay.user.agreement.page.sign
+    ///
+    /// 默认实现返回错误，需要生成url的客户端需要覆盖这个方法。
+    fn generate_url<'a, S, T>(&'a mut self, method: S, _biz_content: T) -> AlipayResult<String>
+    where
+        S: Into<String> + Send + 'a,
+        T: AlipayParams + Send + 'a,
+    {
+        Err(not_implemented("generate_url", method.into()))
+    }
+}
+
+fn not_implemented(name: &str, method: String) -> AlipayError {
+    AlipayError::new(format!("{} is not implemented: {}", name, method))
 }
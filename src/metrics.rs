@@ -0,0 +1,154 @@
+use serde_json::Value;
+use std::time::Duration;
+
+/// 一次网关请求的记录
+///
+/// 只包含接口名和响应的状态信息，不包含请求参数、签名和买家信息。
+/// 发生重试时每次请求都会产生一条记录。
+#[derive(Debug, Clone)]
+pub struct CallRecord {
+    /// 接口名称，如alipay.trade.query
+    pub method: String,
+    /// 第几次请求，从0开始
+    pub attempt: u32,
+    /// 请求耗时
+    pub latency: Duration,
+    /// http状态码，网络错误时为None
+    pub status: Option<u16>,
+    /// 网关返回的code
+    pub code: Option<String>,
+    /// 网关返回的sub_code
+    pub sub_code: Option<String>,
+    /// 网关返回的trace_id，用于向支付宝排查问题
+    pub trace_id: Option<String>,
+    /// 请求失败时的错误信息
+    pub error: Option<String>,
+}
+
+impl CallRecord {
+    /// 请求成功并且网关返回code为10000
+    pub fn is_success(&self) -> bool {
+        self.error.is_none() && self.code.as_deref() == Some("10000")
+    }
+}
+
+/// 指标回调
+///
+/// 每次网关请求结束后都会调用record，可以在这里把数据上报到监控系统。
+/// 闭包`Fn(&CallRecord)`也实现了这个trait。
+///
+/// ```rust
+/// let client = alipay_rs::Client::builder()
+///     .app_id("20210xxxxxxxxxxx")
+///     .metrics(|record: &alipay_rs::CallRecord| {
+///         println!("{} {:?} {:?}", record.method, record.latency, record.code);
+///     })
+///     .finish();
+/// ```
+pub trait Metrics: Send + Sync {
+    fn record(&self, record: &CallRecord);
+}
+
+impl<F> Metrics for F
+where
+    F: Fn(&CallRecord) + Send + Sync,
+{
+    fn record(&self, record: &CallRecord) {
+        self(record)
+    }
+}
+
+// 日志中需要隐藏的字段：签名、授权令牌和买家身份信息
+const REDACTED_KEYS: [&str; 17] = [
+    "sign",
+    "app_auth_token",
+    "auth_token",
+    "auth_code",
+    "buyer_id",
+    "buyer_logon_id",
+    "buyer_open_id",
+    "buyer_user_id",
+    "open_id",
+    "user_id",
+    "logon_id",
+    "identity",
+    "name",
+    "cert_no",
+    "mobile",
+    "phone",
+    "email",
+];
+
+/// 隐藏biz_content中的敏感字段，返回可以写入日志的json字符串
+#[cfg_attr(not(feature = "tracing"), allow(dead_code))]
+pub(crate) fn redact(biz_content: &str) -> String {
+    match serde_json::from_str::<Value>(biz_content) {
+        Ok(mut value) => {
+            redact_value(&mut value);
+            value.to_string()
+        }
+        Err(_) => String::from("***"),
+    }
+}
+
+fn redact_value(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, val) in map.iter_mut() {
+                if REDACTED_KEYS.contains(&key.as_str()) {
+                    *val = Value::String(String::from("***"));
+                } else {
+                    redact_value(val);
+                }
+            }
+        }
+        Value::Array(array) => {
+            for val in array.iter_mut() {
+                redact_value(val);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn redacts_nested_keys() {
+        let content = json!({
+            "out_trade_no": "6823789339978248",
+            "auth_code": "28763443825664394",
+            "payee_info": {"identity": "2088123412341234", "identity_type": "ALIPAY_USER_ID", "name": "张三"},
+            "goods_detail": [{"goods_name": "iphone", "buyer_info": {"mobile": "13800000000"}}],
+        });
+        let redacted: Value = serde_json::from_str(&redact(&content.to_string())).unwrap();
+        assert_eq!(
+            redacted,
+            json!({
+                "out_trade_no": "6823789339978248",
+                "auth_code": "***",
+                "payee_info": {"identity": "***", "identity_type": "ALIPAY_USER_ID", "name": "***"},
+                "goods_detail": [{"goods_name": "iphone", "buyer_info": {"mobile": "***"}}],
+            })
+        );
+    }
+
+    #[test]
+    fn redacts_whole_objects_under_sensitive_keys() {
+        let redacted = redact(r#"{"identity":{"cert_no":"110101199003077777"}}"#);
+        assert_eq!(redacted, r#"{"identity":"***"}"#);
+    }
+
+    #[test]
+    fn non_object_bodies() {
+        assert_eq!(redact(r#"[{"sign":"x"},1]"#), r#"[{"sign":"***"},1]"#);
+        assert_eq!(redact(r#""buyer_id""#), r#""buyer_id""#);
+        assert_eq!(redact("null"), "null");
+        // 不是json时整体隐藏
+        assert_eq!(redact("auth_code=28763443825664394"), "***");
+        assert_eq!(redact(""), "***");
+    }
+}
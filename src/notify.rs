@@ -0,0 +1,113 @@
+//! 异步通知的验签
+
+use crate::{
+    error::{AlipayError, AlipayResult},
+    Sign,
+};
+use serde::de::DeserializeOwned;
+
+/// 验证异步通知的签名，body为通知的表单内容（application/x-www-form-urlencoded）
+///
+/// 除了sign和sign_type之外的非空参数按照key排序后参与验签，验签成功后返回所有参数。
+pub fn verify<S: Sign + ?Sized>(client: &S, body: &str) -> AlipayResult<Vec<(String, String)>> {
+    let params: Vec<(String, String)> = serde_urlencoded::from_str(body)?;
+    let sign = params
+        .iter()
+        .find(|(key, _)| key == "sign")
+        .map(|(_, val)| val.as_str())
+        .ok_or_else(|| AlipayError::new("sign not found in notification"))?;
+    let mut content: Vec<_> = params
+        .iter()
+        .filter(|(key, val)| key != "sign" && key != "sign_type" && !val.is_empty())
+        .collect();
+    content.sort_by(|a, b| a.0.cmp(&b.0));
+    let content = content
+        .iter()
+        .map(|(key, val)| format!("{}={}", key, val))
+        .collect::<Vec<_>>()
+        .join("&");
+    if !client.verify(&content, sign)? {
+        return Err(AlipayError::new("sign verification failed"));
+    }
+    Ok(params)
+}
+
+/// 验签并把通知解析为T
+///
+/// ```rust,no_run
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct TradeNotify {
+///     out_trade_no: String,
+///     trade_status: String,
+/// }
+///
+/// # fn handle(client: &alipay_rs::Client, body: &str) -> alipay_rs::error::AlipayResult<()> {
+/// let notify: TradeNotify = alipay_rs::notify::parse(client, body)?;
+/// // 处理成功后需要返回success
+/// # Ok(())
+/// # }
+/// ```
+pub fn parse<S, T>(client: &S, body: &str) -> AlipayResult<T>
+where
+    S: Sign + ?Sized,
+    T: DeserializeOwned,
+{
+    verify(client, body)?;
+    Ok(serde_urlencoded::from_str(body)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::rsa_signer;
+    use serde::Deserialize;
+
+    const CONTENT: &str = "app_id=2021000000000000&out_trade_no=6823789339978248&total_amount=88.88&trade_status=TRADE_SUCCESS";
+
+    fn body(sign: &str, total_amount: &str) -> String {
+        serde_urlencoded::to_string([
+            ("trade_status", "TRADE_SUCCESS"),
+            ("out_trade_no", "6823789339978248"),
+            ("total_amount", total_amount),
+            ("app_id", "2021000000000000"),
+            ("buyer_logon_id", ""),
+            ("sign_type", "RSA2"),
+            ("sign", sign),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn verifies_sorted_non_empty_params() {
+        let signer = rsa_signer();
+        let sign = signer.sign(CONTENT).unwrap();
+        let params = verify(&signer, &body(&sign, "88.88")).unwrap();
+        assert_eq!(params.len(), 7);
+
+        #[derive(Deserialize)]
+        struct TradeNotify {
+            out_trade_no: String,
+            trade_status: String,
+        }
+        let notify: TradeNotify = parse(&signer, &body(&sign, "88.88")).unwrap();
+        assert_eq!(notify.out_trade_no, "6823789339978248");
+        assert_eq!(notify.trade_status, "TRADE_SUCCESS");
+    }
+
+    #[test]
+    fn rejects_tampered_or_unsigned_notifications() {
+        let signer = rsa_signer();
+        let sign = signer.sign(CONTENT).unwrap();
+        let err = verify(&signer, &body(&sign, "0.01")).unwrap_err();
+        assert!(err.to_string().contains("sign verification failed"));
+
+        let err = verify(&signer, "out_trade_no=6823789339978248").unwrap_err();
+        assert!(err.to_string().contains("sign not found"));
+
+        // 其他密钥的签名
+        let sign = rsa_signer().sign(CONTENT).unwrap();
+        assert!(verify(&signer, &body(&sign, "88.88")).is_err());
+    }
+}
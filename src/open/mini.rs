@@ -0,0 +1,752 @@
+//! 小程序管理相关的接口，alipay.open.mini.*
+//!
+//! 版本上传和提交审核需要上传文件，参数作为表单字段放在请求体中。
+
+use crate::{
+    error::{AlipayError, AlipayResult},
+    response::{BizResponse, Empty},
+    AlipayParams, Cli, Sign, UploadForm,
+};
+use alipay_params::IndexMap;
+use futures::future::{BoxFuture, FutureExt};
+use openssl::{
+    base64,
+    symm::{self, Cipher},
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Map, Value};
+use std::{path::PathBuf, str::FromStr};
+use zeroize::Zeroizing;
+
+/// 支付宝客户端的bundle_id
+pub const ALIPAY_WALLET: &str = "com.alipay.alipaywallet";
+
+/// 小程序版本，bundle_id默认为支付宝客户端
+#[derive(AlipayParams, Debug, Clone, PartialEq, Eq)]
+#[alipay(by_ref)]
+pub struct MiniVersion {
+    #[alipay(required, max_len = 32)]
+    pub app_version: String,
+    #[alipay(required)]
+    pub bundle_id: String,
+}
+
+impl MiniVersion {
+    pub fn new(app_version: &str) -> Self {
+        MiniVersion {
+            app_version: app_version.to_owned(),
+            bundle_id: ALIPAY_WALLET.to_owned(),
+        }
+    }
+}
+
+/// 小程序包
+#[derive(Debug, Clone)]
+pub enum MiniPackage<'a> {
+    /// 本地文件，发送时才会读取
+    Path(PathBuf),
+    /// 内存中的文件
+    Bytes(&'a [u8]),
+}
+
+/// 上传小程序版本，alipay.open.mini.version.upload
+///
+/// 使用模板开发时需要设置template_id，ext为模板的配置（json字符串）。
+#[derive(AlipayParams, Debug, Clone)]
+#[alipay(by_ref)]
+pub struct VersionUploadRequest {
+    #[alipay(flatten)]
+    pub version: MiniVersion,
+    #[alipay(skip_if_none)]
+    pub template_id: Option<String>,
+    #[alipay(skip_if_none)]
+    pub template_version: Option<String>,
+    #[alipay(skip_if_none)]
+    pub ext: Option<String>,
+}
+
+impl VersionUploadRequest {
+    pub fn new(version: MiniVersion) -> Self {
+        VersionUploadRequest {
+            version,
+            template_id: None,
+            template_version: None,
+            ext: None,
+        }
+    }
+}
+
+/// 上传结果，构建是异步的，可以通过version_detail查询
+#[derive(Debug, Clone, Deserialize)]
+pub struct VersionUploadResponse {
+    #[serde(default)]
+    pub build_info: Option<String>,
+    #[serde(default)]
+    pub build_status: Option<String>,
+    #[serde(default)]
+    pub need_rotation: Option<String>,
+}
+
+/// 提交审核，alipay.open.mini.version.audit.apply
+///
+/// 截图等文件通过UploadForm上传，字段名参考支付宝文档，比如first_screen_shot。
+#[derive(AlipayParams, Debug, Clone)]
+#[alipay(by_ref)]
+pub struct AuditApplyRequest {
+    #[alipay(flatten)]
+    pub version: MiniVersion,
+    #[alipay(required, max_len = 500)]
+    pub version_desc: String,
+    /// 审核通过后的可见范围，默认CHINA
+    #[alipay(required)]
+    pub region_type: String,
+    #[alipay(skip_if_none, max_len = 200)]
+    pub memo: Option<String>,
+    /// 是否加急审核
+    #[alipay(skip_if_none)]
+    pub speed_up: Option<bool>,
+    /// 审核通过后是否自动上架
+    #[alipay(skip_if_none)]
+    pub auto_online: Option<bool>,
+}
+
+impl AuditApplyRequest {
+    pub fn new(version: MiniVersion, version_desc: &str) -> Self {
+        AuditApplyRequest {
+            version,
+            version_desc: version_desc.to_owned(),
+            region_type: "CHINA".to_owned(),
+            memo: None,
+            speed_up: None,
+            auto_online: None,
+        }
+    }
+}
+
+/// 提交审核的结果
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuditApplyResponse {
+    #[serde(default)]
+    pub speed_up: Option<String>,
+    #[serde(default)]
+    pub speed_up_memo: Option<String>,
+}
+
+/// 版本状态
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum VersionStatus {
+    /// 开发中
+    Init,
+    /// 审核中
+    Auditing,
+    /// 审核驳回
+    AuditReject,
+    /// 待上架
+    WaitRelease,
+    /// 准入不可营销
+    BaseAuditPass,
+    /// 灰度中
+    Gray,
+    /// 已上架
+    Release,
+    /// 已下架
+    Offline,
+    /// 被强制下架
+    AuditOffline,
+    #[serde(other)]
+    Unknown,
+}
+
+/// 版本列表中的版本
+#[derive(Debug, Clone, Deserialize)]
+pub struct VersionInfo {
+    pub app_version: String,
+    #[serde(default)]
+    pub bundle_id: Option<String>,
+    #[serde(default)]
+    pub version_status: Option<VersionStatus>,
+    #[serde(default)]
+    pub version_description: Option<String>,
+    #[serde(default)]
+    pub create_time: Option<String>,
+}
+
+/// 版本列表，alipay.open.mini.version.list.query
+#[derive(Debug, Clone, Deserialize)]
+pub struct VersionList {
+    #[serde(default)]
+    pub app_versions: Vec<String>,
+    #[serde(default)]
+    pub app_version_infos: Vec<VersionInfo>,
+}
+
+/// 版本详情，alipay.open.mini.version.detail.query
+#[derive(Debug, Clone, Deserialize)]
+pub struct VersionDetail {
+    pub app_version: String,
+    #[serde(default)]
+    pub status: Option<VersionStatus>,
+    #[serde(default)]
+    pub version_desc: Option<String>,
+    #[serde(default)]
+    pub reject_reason: Option<String>,
+    #[serde(default)]
+    pub build_status: Option<String>,
+    #[serde(default)]
+    pub gmt_create: Option<String>,
+    #[serde(default)]
+    pub gmt_apply_audit: Option<String>,
+    #[serde(default)]
+    pub gmt_online: Option<String>,
+    #[serde(default)]
+    pub gmt_offline: Option<String>,
+}
+
+/// 体验版状态，alipay.open.mini.experience.query
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExperienceStatus {
+    #[serde(default)]
+    pub status: Option<String>,
+    /// 体验版二维码，打包完成后才有
+    #[serde(default)]
+    pub exp_qr_code_url: Option<String>,
+}
+
+impl ExperienceStatus {
+    /// 体验版是否已经打包完成
+    pub fn is_ready(&self) -> bool {
+        self.status.as_deref() == Some("expVersionPackaged")
+    }
+}
+
+/// 模板消息，alipay.open.app.mini.templatemessage.send
+///
+/// data中的每一项会转换为{"keyword": {"value": "..."}}。
+#[derive(Debug, Clone, Default)]
+pub struct TemplateMessage {
+    pub to_user_id: String,
+    pub user_template_id: String,
+    pub page: String,
+    pub data: IndexMap<String, String>,
+    pub form_id: Option<String>,
+}
+
+impl TemplateMessage {
+    pub fn new(to_user_id: &str, user_template_id: &str, page: &str) -> Self {
+        TemplateMessage {
+            to_user_id: to_user_id.to_owned(),
+            user_template_id: user_template_id.to_owned(),
+            page: page.to_owned(),
+            ..Default::default()
+        }
+    }
+    /// 添加模板中的关键字
+    pub fn keyword(&mut self, key: &str, value: &str) -> &mut Self {
+        self.data.insert(key.to_owned(), value.to_owned());
+        self
+    }
+}
+
+/// 分页查询商品，alipay.open.mini.item.page.query
+#[derive(AlipayParams, Debug, Clone)]
+#[alipay(by_ref)]
+pub struct ItemPageQuery {
+    #[alipay(range(min = 1))]
+    pub page_num: u32,
+    #[alipay(range(min = 1, max = 100))]
+    pub page_size: u32,
+    #[alipay(skip_if_none)]
+    pub item_id_list: Option<Vec<String>>,
+}
+
+impl ItemPageQuery {
+    pub fn new(page_num: u32, page_size: u32) -> Self {
+        ItemPageQuery {
+            page_num,
+            page_size,
+            item_id_list: None,
+        }
+    }
+}
+
+/// 商品，未定义的字段保存在extra中
+#[derive(Debug, Clone, Deserialize)]
+pub struct MiniItem {
+    #[serde(default)]
+    pub item_id: Option<String>,
+    #[serde(default)]
+    pub out_item_id: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// 商品分页
+#[derive(Debug, Clone, Deserialize)]
+pub struct ItemPage {
+    #[serde(default, alias = "item_list")]
+    pub items: Vec<MiniItem>,
+    #[serde(default)]
+    pub total_number: Option<u64>,
+}
+
+/// 小程序前端获取的敏感数据，比如my.getPhoneNumber返回的response
+///
+/// 可以直接从前端传来的json字符串解析：`let data: EncryptedData = response.parse()?;`
+#[derive(Debug, Clone, Deserialize)]
+pub struct EncryptedData {
+    pub response: String,
+    pub sign: String,
+    #[serde(default)]
+    pub sign_type: Option<String>,
+    /// 为AES时response是加密的内容
+    #[serde(default)]
+    pub encrypt_type: Option<String>,
+    #[serde(default)]
+    pub charset: Option<String>,
+}
+
+impl FromStr for EncryptedData {
+    type Err = AlipayError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+impl EncryptedData {
+    pub fn is_encrypted(&self) -> bool {
+        self.encrypt_type.is_some()
+    }
+}
+
+/// 用户绑定的手机号，my.getPhoneNumber
+#[derive(Debug, Clone, Deserialize)]
+pub struct PhoneNumber {
+    pub mobile: String,
+}
+
+/// 验证签名并解密小程序前端获取的敏感数据
+///
+/// client用于验签（支付宝公钥），aes_key是开放平台中设置的接口内容加密密钥（base64）。
+/// 解密后的code不是10000时返回错误。
+///
+/// ```rust,no_run
+/// use alipay_rs::open::mini::{self, EncryptedData, PhoneNumber};
+///
+/// # fn phone(client: &alipay_rs::Client, response: &str) -> alipay_rs::error::AlipayResult<()> {
+/// let data: EncryptedData = response.parse()?;
+/// let aes_key = client.aes_key().map(|key| key.expose()).unwrap_or_default();
+/// let phone: PhoneNumber = mini::decrypt_data(client, aes_key, &data)?;
+/// println!("{}", phone.mobile);
+/// # Ok(())
+/// # }
+/// ```
+pub fn decrypt_data<S, T>(client: &S, aes_key: &str, data: &EncryptedData) -> AlipayResult<T>
+where
+    S: Sign + ?Sized,
+    T: DeserializeOwned,
+{
+    // 加密时签名的内容是带引号的密文
+    let verified = if data.is_encrypted() {
+        client.verify(&format!("\"{}\"", data.response), &data.sign)?
+    } else {
+        client.verify(&data.response, &data.sign)?
+    };
+    if !verified {
+        return Err(AlipayError::new("sign verification failed"));
+    }
+    let content = match data.encrypt_type.as_deref() {
+        None => data.response.clone(),
+        Some("AES") => aes_decrypt(aes_key, &data.response)?,
+        Some(other) => {
+            return Err(AlipayError::new(format!(
+                "unsupported encrypt type: {}",
+                other
+            )))
+        }
+    };
+    serde_json::from_str::<BizResponse<T>>(&content)?.into_result()
+}
+
+/// AES/CBC/PKCS5Padding，iv为16个0
+fn aes_decrypt(aes_key: &str, content: &str) -> AlipayResult<String> {
+    let key = Zeroizing::new(base64::decode_block(aes_key)?);
+    let cipher = match key.len() {
+        16 => Cipher::aes_128_cbc(),
+        24 => Cipher::aes_192_cbc(),
+        32 => Cipher::aes_256_cbc(),
+        len => return Err(AlipayError::new(format!("invalid aes key length: {}", len))),
+    };
+    let plain = symm::decrypt(
+        cipher,
+        &key,
+        Some(&[0u8; 16]),
+        &base64::decode_block(content)?,
+    )?;
+    Ok(String::from_utf8(plain)?)
+}
+
+#[derive(AlipayParams)]
+struct TemplateMessageParams<'a> {
+    to_user_id: &'a str,
+    user_template_id: &'a str,
+    page: &'a str,
+    data: String,
+    #[alipay(skip_if_none)]
+    form_id: Option<&'a str>,
+}
+
+#[derive(AlipayParams)]
+struct BundleQuery<'a> {
+    bundle_id: &'a str,
+}
+
+/// 小程序管理接口
+///
+/// ```rust,no_run
+/// use alipay_rs::open::mini::{MiniApi, MiniPackage, MiniVersion, VersionUploadRequest};
+///
+/// # async fn upload(client: &alipay_rs::Client) -> alipay_rs::error::AlipayResult<()> {
+/// let version = MiniVersion::new("0.0.2");
+/// client
+///     .version_upload(
+///         &VersionUploadRequest::new(version.clone()),
+///         MiniPackage::Path("./dist.zip".into()),
+///     )
+///     .await?;
+/// client.experience_create(&version).await?;
+/// let status = client.experience_query(&version).await?;
+/// println!("{:?}", status.exp_qr_code_url);
+/// # Ok(())
+/// # }
+/// ```
+pub trait MiniApi {
+    /// 上传小程序包
+    fn version_upload<'a>(
+        &'a self,
+        request: &'a VersionUploadRequest,
+        package: MiniPackage<'a>,
+    ) -> BoxFuture<'a, AlipayResult<VersionUploadResponse>>;
+    /// 提交审核，files中是截图等文件，没有时传UploadForm::new()
+    fn audit_apply<'a>(
+        &'a self,
+        request: &'a AuditApplyRequest,
+        files: UploadForm<'a>,
+    ) -> BoxFuture<'a, AlipayResult<AuditApplyResponse>>;
+    /// 撤销审核
+    fn audit_cancel<'a>(&'a self, version: &'a MiniVersion) -> BoxFuture<'a, AlipayResult<()>>;
+    /// 上架
+    fn online<'a>(&'a self, version: &'a MiniVersion) -> BoxFuture<'a, AlipayResult<()>>;
+    /// 下架
+    fn offline<'a>(&'a self, version: &'a MiniVersion) -> BoxFuture<'a, AlipayResult<()>>;
+    /// 回滚到上一个上架的版本
+    fn rollback<'a>(&'a self, version: &'a MiniVersion) -> BoxFuture<'a, AlipayResult<()>>;
+    /// 设置为体验版
+    fn experience_create<'a>(&'a self, version: &'a MiniVersion)
+        -> BoxFuture<'a, AlipayResult<()>>;
+    /// 查询体验版状态和二维码
+    fn experience_query<'a>(
+        &'a self,
+        version: &'a MiniVersion,
+    ) -> BoxFuture<'a, AlipayResult<ExperienceStatus>>;
+    /// 取消体验版
+    fn experience_cancel<'a>(&'a self, version: &'a MiniVersion)
+        -> BoxFuture<'a, AlipayResult<()>>;
+    /// 查询版本列表
+    fn version_list<'a>(&'a self, bundle_id: &'a str) -> BoxFuture<'a, AlipayResult<VersionList>>;
+    /// 查询版本详情
+    fn version_detail<'a>(
+        &'a self,
+        version: &'a MiniVersion,
+    ) -> BoxFuture<'a, AlipayResult<VersionDetail>>;
+    /// 发送模板消息
+    fn send_template_message<'a>(
+        &'a self,
+        message: &'a TemplateMessage,
+    ) -> BoxFuture<'a, AlipayResult<()>>;
+    /// 分页查询商品
+    fn item_page_query<'a>(
+        &'a self,
+        query: &'a ItemPageQuery,
+    ) -> BoxFuture<'a, AlipayResult<ItemPage>>;
+}
+
+impl<C> MiniApi for C
+where
+    C: Cli + Sync,
+{
+    fn version_upload<'a>(
+        &'a self,
+        request: &'a VersionUploadRequest,
+        package: MiniPackage<'a>,
+    ) -> BoxFuture<'a, AlipayResult<VersionUploadResponse>> {
+        async move {
+            let method = "alipay.open.mini.version.upload";
+            let mut form = UploadForm::new();
+            match package {
+                MiniPackage::Path(path) => {
+                    form.path("app_version_package", path, Some("application/zip"));
+                }
+                MiniPackage::Bytes(content) => {
+                    form.bytes(
+                        "app_version_package",
+                        "dist.zip",
+                        content,
+                        Some("application/zip"),
+                    );
+                }
+            }
+            let response = self.post_multipart(method, request, form).await?;
+            response.biz_response(method)?.into_result()
+        }
+        .boxed()
+    }
+
+    fn audit_apply<'a>(
+        &'a self,
+        request: &'a AuditApplyRequest,
+        files: UploadForm<'a>,
+    ) -> BoxFuture<'a, AlipayResult<AuditApplyResponse>> {
+        async move {
+            let method = "alipay.open.mini.version.audit.apply";
+            let response = self.post_multipart(method, request, files).await?;
+            response.biz_response(method)?.into_result()
+        }
+        .boxed()
+    }
+
+    fn audit_cancel<'a>(&'a self, version: &'a MiniVersion) -> BoxFuture<'a, AlipayResult<()>> {
+        version_action(self, "alipay.open.mini.version.audit.cancel", version)
+    }
+
+    fn online<'a>(&'a self, version: &'a MiniVersion) -> BoxFuture<'a, AlipayResult<()>> {
+        version_action(self, "alipay.open.mini.version.online", version)
+    }
+
+    fn offline<'a>(&'a self, version: &'a MiniVersion) -> BoxFuture<'a, AlipayResult<()>> {
+        version_action(self, "alipay.open.mini.version.offline", version)
+    }
+
+    fn rollback<'a>(&'a self, version: &'a MiniVersion) -> BoxFuture<'a, AlipayResult<()>> {
+        version_action(self, "alipay.open.mini.version.rollback", version)
+    }
+
+    fn experience_create<'a>(
+        &'a self,
+        version: &'a MiniVersion,
+    ) -> BoxFuture<'a, AlipayResult<()>> {
+        version_action(self, "alipay.open.mini.experience.create", version)
+    }
+
+    fn experience_query<'a>(
+        &'a self,
+        version: &'a MiniVersion,
+    ) -> BoxFuture<'a, AlipayResult<ExperienceStatus>> {
+        async move {
+            let method = "alipay.open.mini.experience.query";
+            let response = self.post(method, version).await?;
+            response.biz_response(method)?.into_result()
+        }
+        .boxed()
+    }
+
+    fn experience_cancel<'a>(
+        &'a self,
+        version: &'a MiniVersion,
+    ) -> BoxFuture<'a, AlipayResult<()>> {
+        version_action(self, "alipay.open.mini.experience.cancel", version)
+    }
+
+    fn version_list<'a>(&'a self, bundle_id: &'a str) -> BoxFuture<'a, AlipayResult<VersionList>> {
+        async move {
+            let method = "alipay.open.mini.version.list.query";
+            let response = self.post(method, BundleQuery { bundle_id }).await?;
+            response.biz_response(method)?.into_result()
+        }
+        .boxed()
+    }
+
+    fn version_detail<'a>(
+        &'a self,
+        version: &'a MiniVersion,
+    ) -> BoxFuture<'a, AlipayResult<VersionDetail>> {
+        async move {
+            let method = "alipay.open.mini.version.detail.query";
+            let response = self.post(method, version).await?;
+            response.biz_response(method)?.into_result()
+        }
+        .boxed()
+    }
+
+    fn send_template_message<'a>(
+        &'a self,
+        message: &'a TemplateMessage,
+    ) -> BoxFuture<'a, AlipayResult<()>> {
+        async move {
+            let method = "alipay.open.app.mini.templatemessage.send";
+            let data: Map<String, Value> = message
+                .data
+                .iter()
+                .map(|(key, val)| (key.clone(), json!({ "value": val })))
+                .collect();
+            let params = TemplateMessageParams {
+                to_user_id: &message.to_user_id,
+                user_template_id: &message.user_template_id,
+                page: &message.page,
+                data: serde_json::to_string(&data)?,
+                form_id: message.form_id.as_deref(),
+            };
+            let response = self.post(method, params).await?;
+            response.biz_response::<Empty>(method)?.into_result()?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn item_page_query<'a>(
+        &'a self,
+        query: &'a ItemPageQuery,
+    ) -> BoxFuture<'a, AlipayResult<ItemPage>> {
+        async move {
+            let method = "alipay.open.mini.item.page.query";
+            let response = self.post(method, query).await?;
+            response.biz_response(method)?.into_result()
+        }
+        .boxed()
+    }
+}
+
+/// 只需要版本号、没有业务字段返回的接口
+fn version_action<'a, C: Cli + Sync>(
+    client: &'a C,
+    method: &'static str,
+    version: &'a MiniVersion,
+) -> BoxFuture<'a, AlipayResult<()>> {
+    async move {
+        let response = client.post(method, version).await?;
+        response.biz_response::<Empty>(method)?.into_result()?;
+        Ok(())
+    }
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{client, rsa_signer, serve_biz, AES_KEY};
+    use futures::executor::block_on;
+
+    const DETAIL: &str = "alipay.open.mini.version.detail.query";
+    const LIST: &str = "alipay.open.mini.version.list.query";
+
+    #[test]
+    fn version_detail() {
+        let (url, handle) = serve_biz(&[(
+            DETAIL,
+            r#"{"code":"10000","msg":"Success","app_version":"0.0.1","status":"AUDITING"}"#,
+        )]);
+        let detail = block_on(client(&url).version_detail(&MiniVersion::new("0.0.1"))).unwrap();
+        handle.join().unwrap();
+        assert_eq!(detail.app_version, "0.0.1");
+        assert!(matches!(detail.status, Some(VersionStatus::Auditing)));
+    }
+
+    #[test]
+    fn version_errors_are_biz_errors() {
+        let (url, handle) = serve_biz(&[
+            (
+                DETAIL,
+                r#"{"code":"40004","msg":"Business Failed","sub_code":"MINI_APP_VERSION_NOT_EXIST","sub_msg":"版本不存在"}"#,
+            ),
+            (
+                LIST,
+                r#"{"code":"40006","msg":"Insufficient Permissions","sub_code":"isv.insufficient-isv-permissions","sub_msg":"ISV权限不足"}"#,
+            ),
+        ]);
+        let client = client(&url);
+        let err = block_on(client.version_detail(&MiniVersion::new("0.0.1"))).unwrap_err();
+        let biz = err.biz_error().unwrap();
+        assert_eq!(biz.code, "40004");
+        assert_eq!(biz.sub_code, "MINI_APP_VERSION_NOT_EXIST");
+
+        let err = block_on(client.version_list(ALIPAY_WALLET)).unwrap_err();
+        assert_eq!(err.biz_error().unwrap().code, "40006");
+        handle.join().unwrap();
+    }
+
+    fn aes_encrypt(content: &str) -> String {
+        let key = base64::decode_block(AES_KEY).unwrap();
+        let encrypted = symm::encrypt(
+            Cipher::aes_128_cbc(),
+            &key,
+            Some(&[0u8; 16]),
+            content.as_bytes(),
+        )
+        .unwrap();
+        base64::encode_block(&encrypted)
+    }
+
+    #[test]
+    fn decrypt_data_round_trip() {
+        let signer = rsa_signer();
+        let response = aes_encrypt(r#"{"code":"10000","msg":"Success","mobile":"13800000000"}"#);
+        let data = EncryptedData {
+            sign: signer.sign(&format!("\"{}\"", response)).unwrap(),
+            response,
+            sign_type: Some("RSA2".to_owned()),
+            encrypt_type: Some("AES".to_owned()),
+            charset: Some("UTF-8".to_owned()),
+        };
+        let phone: PhoneNumber = decrypt_data(&signer, AES_KEY, &data).unwrap();
+        assert_eq!(phone.mobile, "13800000000");
+    }
+
+    #[test]
+    fn decrypt_data_without_encryption() {
+        let signer = rsa_signer();
+        let response = r#"{"code":"10000","msg":"Success","mobile":"13800000000"}"#;
+        let data: EncryptedData = json!({
+            "response": response,
+            "sign": signer.sign(response).unwrap(),
+        })
+        .to_string()
+        .parse()
+        .unwrap();
+        let phone: PhoneNumber = decrypt_data(&signer, AES_KEY, &data).unwrap();
+        assert_eq!(phone.mobile, "13800000000");
+    }
+
+    #[test]
+    fn decrypt_data_rejects_bad_signatures_and_failures() {
+        let signer = rsa_signer();
+        let response = aes_encrypt(r#"{"code":"10000","msg":"Success","mobile":"13800000000"}"#);
+        let data = EncryptedData {
+            // 签名的内容少了引号
+            sign: signer.sign(&response).unwrap(),
+            response,
+            sign_type: None,
+            encrypt_type: Some("AES".to_owned()),
+            charset: None,
+        };
+        let err = decrypt_data::<_, PhoneNumber>(&signer, AES_KEY, &data).unwrap_err();
+        assert!(err.to_string().contains("sign verification failed"));
+
+        let response = aes_encrypt(
+            r#"{"code":"40003","msg":"Insufficient Conditions","sub_code":"isv.invalid-auth-relations","sub_msg":"无效的授权关系"}"#,
+        );
+        let data = EncryptedData {
+            sign: signer.sign(&format!("\"{}\"", response)).unwrap(),
+            response,
+            sign_type: None,
+            encrypt_type: Some("AES".to_owned()),
+            charset: None,
+        };
+        let err = decrypt_data::<_, PhoneNumber>(&signer, AES_KEY, &data).unwrap_err();
+        assert_eq!(err.biz_error().unwrap().code, "40003");
+    }
+}
@@ -0,0 +1,3 @@
+//! 开放平台相关的接口
+
+pub mod mini;
@@ -0,0 +1,319 @@
+use crate::error::{AlipayError, AlipayResult};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// 客户端限流，令牌桶算法
+///
+/// 应用级别的限流对所有接口生效，接口级别的限流对每个接口单独计数，两者同时生效。
+/// 令牌不足时等待，等待时间超过max_wait时直接返回错误，不会发出请求。
+/// 异步接口等待时不会阻塞线程，同步接口阻塞当前线程等待。
+/// 同一个Client的所有克隆以及set_public_params生成的ClientWithParams共享同一组令牌桶。
+///
+/// 每秒请求数和突发请求数必须大于0，burst只作用于应用级别的限流，
+/// 不满足时这个客户端的所有请求都会返回错误。
+///
+/// ```rust
+/// use std::time::Duration;
+/// use alipay_rs::RateLimit;
+///
+/// let client = alipay_rs::Client::builder()
+///     .app_id("20210xxxxxxxxxxx")
+///     .rate_limit(
+///         RateLimit::new(100)
+///             .per_method(20)
+///             .method("alipay.trade.pay", 10)
+///             .max_wait(Duration::from_secs(1)),
+///     )
+///     .finish();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RateLimit {
+    app: Option<Quota>,
+    per_method: Option<Quota>,
+    methods: HashMap<String, Quota>,
+    max_wait: Option<Duration>,
+    /// 第一个无效的设置
+    invalid: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Quota {
+    per_second: f64,
+    burst: f64,
+}
+
+impl Quota {
+    /// per_second为0时返回None
+    fn new(per_second: u32) -> Option<Self> {
+        (per_second > 0).then_some(Quota {
+            per_second: per_second as f64,
+            burst: per_second as f64,
+        })
+    }
+}
+
+impl RateLimit {
+    /// per_second: 应用每秒最多发出的请求数，默认允许per_second个请求的突发
+    pub fn new(per_second: u32) -> Self {
+        let mut rate_limit = RateLimit::default();
+        rate_limit.app = rate_limit.quota("per_second", per_second);
+        rate_limit
+    }
+    /// 不限制应用的请求数，只限制设置了的接口
+    pub fn unlimited() -> Self {
+        RateLimit::default()
+    }
+    /// 应用级别的令牌桶容量，即允许的突发请求数，需要先通过new设置应用每秒的请求数
+    pub fn burst(mut self, burst: u32) -> Self {
+        if burst == 0 {
+            self.reject("burst must be greater than 0");
+        } else if let Some(quota) = &mut self.app {
+            quota.burst = burst as f64;
+        } else {
+            self.reject("burst requires an app level per_second");
+        }
+        self
+    }
+    /// 每个接口每秒最多发出的请求数
+    pub fn per_method(mut self, per_second: u32) -> Self {
+        self.per_method = self.quota("per_method", per_second);
+        self
+    }
+    /// 单独设置某个接口每秒最多发出的请求数，覆盖per_method
+    pub fn method(mut self, method: &str, per_second: u32) -> Self {
+        if let Some(quota) = self.quota(method, per_second) {
+            self.methods.insert(method.to_owned(), quota);
+        }
+        self
+    }
+    /// 最长等待时间，超过时返回错误；为0时令牌不足立即返回错误；默认一直等待
+    pub fn max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = Some(max_wait);
+        self
+    }
+
+    fn quota(&mut self, name: &str, per_second: u32) -> Option<Quota> {
+        let quota = Quota::new(per_second);
+        if quota.is_none() {
+            self.reject(&format!("{} must be greater than 0", name));
+        }
+        quota
+    }
+
+    fn reject(&mut self, reason: &str) {
+        self.invalid.get_or_insert_with(|| reason.to_owned());
+    }
+
+    fn method_quota(&self, method: &str) -> Option<Quota> {
+        self.methods.get(method).copied().or(self.per_method)
+    }
+}
+
+struct Bucket {
+    quota: Quota,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(quota: Quota, now: Instant) -> Self {
+        Bucket {
+            quota,
+            tokens: quota.burst,
+            updated: now,
+        }
+    }
+
+    /// 补充令牌后返回拿到一个令牌需要等待的时间
+    fn wait_time(&mut self, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.quota.per_second).min(self.quota.burst);
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / self.quota.per_second)
+        }
+    }
+}
+
+struct Buckets {
+    app: Option<Bucket>,
+    methods: HashMap<String, Bucket>,
+}
+
+/// 由Transport持有的限流器
+pub(crate) struct RateLimiter {
+    config: RateLimit,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(config: RateLimit) -> Self {
+        let now = Instant::now();
+        RateLimiter {
+            buckets: Mutex::new(Buckets {
+                app: config.app.map(|quota| Bucket::new(quota, now)),
+                methods: HashMap::new(),
+            }),
+            config,
+        }
+    }
+
+    /// 预留发送一个请求的令牌，返回需要等待的时间，等待结束后直接发送
+    pub(crate) fn reserve(&self, method: &str) -> AlipayResult<Duration> {
+        if let Some(invalid) = &self.config.invalid {
+            return Err(AlipayError::new(format!("invalid rate limit: {}", invalid)));
+        }
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let Buckets { app, methods } = &mut *buckets;
+        let mut limits: Vec<&mut Bucket> = app.iter_mut().collect();
+        if let Some(quota) = self.config.method_quota(method) {
+            limits.push(
+                methods
+                    .entry(method.to_owned())
+                    .or_insert_with(|| Bucket::new(quota, now)),
+            );
+        }
+        let wait = limits
+            .iter_mut()
+            .map(|bucket| bucket.wait_time(now))
+            .max()
+            .unwrap_or(Duration::ZERO);
+        if self.config.max_wait.is_some_and(|max_wait| wait > max_wait) {
+            return Err(AlipayError::new(format!("rate limit exceeded: {}", method)));
+        }
+        for bucket in limits {
+            bucket.tokens -= 1.0;
+        }
+        Ok(wait)
+    }
+
+    /// 获取令牌，令牌不足时阻塞当前线程等待
+    pub(crate) fn acquire(&self, method: &str) -> AlipayResult<()> {
+        let wait = self.reserve(method)?;
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_refills_over_time() {
+        let start = Instant::now();
+        let quota = Quota {
+            per_second: 10.0,
+            burst: 2.0,
+        };
+        let mut bucket = Bucket::new(quota, start);
+        for _ in 0..2 {
+            assert_eq!(bucket.wait_time(start), Duration::ZERO);
+            bucket.tokens -= 1.0;
+        }
+        assert_eq!(bucket.wait_time(start), Duration::from_millis(100));
+        assert_eq!(
+            bucket.wait_time(start + Duration::from_millis(50)),
+            Duration::from_millis(50)
+        );
+        assert_eq!(
+            bucket.wait_time(start + Duration::from_millis(100)),
+            Duration::ZERO
+        );
+        // 空闲时令牌不会超过容量
+        bucket.wait_time(start + Duration::from_secs(10));
+        assert_eq!(bucket.tokens, 2.0);
+    }
+
+    #[test]
+    fn burst_is_served_immediately() {
+        let limiter = RateLimiter::new(RateLimit::new(10).burst(3));
+        for _ in 0..3 {
+            assert_eq!(
+                limiter.reserve("alipay.trade.query").unwrap(),
+                Duration::ZERO
+            );
+        }
+        let wait = limiter.reserve("alipay.trade.query").unwrap();
+        assert!(wait > Duration::from_millis(50) && wait <= Duration::from_millis(100));
+        // 令牌已经被预留，下一个请求排在后面
+        let wait = limiter.reserve("alipay.trade.query").unwrap();
+        assert!(wait > Duration::from_millis(150) && wait <= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn methods_are_limited_separately() {
+        let limiter = RateLimiter::new(
+            RateLimit::unlimited()
+                .per_method(1)
+                .method("alipay.trade.pay", 2),
+        );
+        assert_eq!(
+            limiter.reserve("alipay.trade.query").unwrap(),
+            Duration::ZERO
+        );
+        assert!(limiter.reserve("alipay.trade.query").unwrap() > Duration::ZERO);
+        assert_eq!(
+            limiter.reserve("alipay.trade.refund").unwrap(),
+            Duration::ZERO
+        );
+        assert_eq!(limiter.reserve("alipay.trade.pay").unwrap(), Duration::ZERO);
+        assert_eq!(limiter.reserve("alipay.trade.pay").unwrap(), Duration::ZERO);
+        assert!(limiter.reserve("alipay.trade.pay").unwrap() > Duration::ZERO);
+    }
+
+    #[test]
+    fn max_wait_rejects_long_waits() {
+        let limiter = RateLimiter::new(RateLimit::new(1).max_wait(Duration::ZERO));
+        limiter.reserve("alipay.trade.query").unwrap();
+        let err = limiter.reserve("alipay.trade.query").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("rate limit exceeded: alipay.trade.query"));
+    }
+
+    #[test]
+    fn zero_is_rejected() {
+        for (config, name) in [
+            (RateLimit::new(0), "per_second"),
+            (RateLimit::new(10).burst(0), "burst"),
+            (RateLimit::new(10).per_method(0), "per_method"),
+            (
+                RateLimit::new(10).method("alipay.trade.pay", 0),
+                "alipay.trade.pay",
+            ),
+        ] {
+            let err = RateLimiter::new(config)
+                .reserve("alipay.trade.pay")
+                .unwrap_err();
+            assert!(err.to_string().contains(&format!(
+                "invalid rate limit: {} must be greater than 0",
+                name
+            )));
+        }
+    }
+
+    #[test]
+    fn burst_without_app_quota_is_rejected() {
+        for config in [
+            RateLimit::unlimited().burst(5),
+            RateLimit::unlimited().per_method(10).burst(5),
+        ] {
+            let err = RateLimiter::new(config)
+                .reserve("alipay.trade.pay")
+                .unwrap_err();
+            assert!(err
+                .to_string()
+                .contains("invalid rate limit: burst requires an app level per_second"));
+        }
+    }
+}
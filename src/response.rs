@@ -1,29 +1,77 @@
-use crate::error::AlipayResult as Result;
-use serde::de::DeserializeOwned;
-use std::io::Read;
+use crate::error::{AlipayError, AlipayResult as Result, BizError};
+use serde::{
+    de::{self, DeserializeOwned},
+    Deserialize, Deserializer,
+};
+use serde_json::Value;
+use std::{
+    io::{Cursor, Read},
+    result::Result as StdResult,
+};
 use ureq::Response as UreqResponse;
 
-pub struct Response(UreqResponse);
+/// 网关响应
+///
+/// 响应体在收到时会被完整读取，因此可以在重试判断之后再交给调用方。
+pub struct Response {
+    url: String,
+    http_version: String,
+    status: u16,
+    status_text: String,
+    headers: Vec<(String, String)>,
+    content_type: String,
+    charset: String,
+    body: String,
+}
 
 impl Response {
-    pub fn new(resp: UreqResponse) -> Self {
-        Response(resp)
+    /// 读取完整的响应体，读取失败时返回错误
+    ///
+    /// 0.5.0起返回`Result`，之前的版本不读取响应体，直接返回`Response`。
+    pub fn new(resp: UreqResponse) -> Result<Self> {
+        let headers = resp
+            .headers_names()
+            .into_iter()
+            .flat_map(|name| {
+                resp.all(&name)
+                    .into_iter()
+                    .map(|val| (name.clone(), val.to_owned()))
+                    .collect::<Vec<(String, String)>>()
+            })
+            .collect();
+        let url = resp.get_url().to_owned();
+        let http_version = resp.http_version().to_owned();
+        let status = resp.status();
+        let status_text = resp.status_text().to_owned();
+        let content_type = resp.content_type().to_owned();
+        let charset = resp.charset().to_owned();
+        let body = resp.into_string()?;
+        Ok(Response {
+            url,
+            http_version,
+            status,
+            status_text,
+            headers,
+            content_type,
+            charset,
+            body,
+        })
     }
 
     /// The URL we ended up at. This can differ from the request url when
     /// we have followed redirects.
     pub fn get_url(&self) -> &str {
-        self.0.get_url()
+        &self.url
     }
 
     /// The http version: `HTTP/1.1`
     pub fn http_version(&self) -> &str {
-        self.0.http_version()
+        &self.http_version
     }
 
     /// The status as a u16: `200`
     pub fn status(&self) -> u16 {
-        self.0.status()
+        self.status
     }
 
     /// The status text: `OK`
@@ -31,7 +79,7 @@ impl Response {
     /// The HTTP spec allows for non-utf8 status texts. This uses from_utf8_lossy to
     /// convert such lines to &str.
     pub fn status_text(&self) -> &str {
-        self.0.status_text()
+        &self.status_text
     }
 
     /// The header value for the given name, or None if not found.
@@ -40,69 +88,82 @@ impl Response {
     /// to be encoded using encodings like iso-8859-1. Such encodings
     /// means the values are not possible to interpret as utf-8.
     ///
-    /// In case the header value can't be read as utf-8, this function
-    /// returns `None` (while the name is visible in [`Response::headers_names()`]).
+    /// Headers whose value can't be read as utf-8 are dropped when the
+    /// response is buffered, so this function returns `None` for them and
+    /// they are not listed in [`Response::headers_names()`] either.
     pub fn header(&self, name: &str) -> Option<&str> {
-        self.0.header(name)
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, val)| val.as_str())
     }
 
     /// A list of the header names in this response.
     /// Lowercased to be uniform.
     ///
-    /// It's possible for a header name to be returned by this function, and
-    /// still give a `None` value. See [`Response::header()`] for an explanation
-    /// as to why.
+    /// Every name returned here has a value in [`Response::header()`];
+    /// headers with non-utf8 values are not kept.
     pub fn headers_names(&self) -> Vec<String> {
-        self.0.headers_names()
+        let mut names: Vec<String> = Vec::with_capacity(self.headers.len());
+        for (key, _) in self.headers.iter() {
+            if !names.contains(key) {
+                names.push(key.to_owned());
+            }
+        }
+        names
     }
 
     /// Tells if the response has the named header.
     pub fn has(&self, name: &str) -> bool {
-        self.0.has(name)
+        self.header(name).is_some()
     }
 
     /// All headers corresponding values for the give name, or empty vector.
     pub fn all(&self, name: &str) -> Vec<&str> {
-        self.0.all(name)
+        self.headers
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, val)| val.as_str())
+            .collect()
     }
 
     /// The content type part of the "Content-Type" header without
     /// the charset.
     pub fn content_type(&self) -> &str {
-        self.0.content_type()
+        &self.content_type
     }
 
     /// The character set part of the "Content-Type".
     pub fn charset(&self) -> &str {
-        self.0.charset()
+        &self.charset
+    }
+
+    /// 响应体，可以在拦截器中读取而不消耗响应
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+
+    /// 替换响应体，用于在拦截器中改写响应
+    pub fn set_body<S: Into<String>>(&mut self, body: S) {
+        self.body = body.into();
     }
 
     /// Turn this response into a `impl Read` of the body.
     ///
-    /// 1. If `Transfer-Encoding: chunked`, the returned reader will unchunk it
-    ///    and any `Content-Length` header is ignored.
-    /// 2. If `Content-Length` is set, the returned reader is limited to this byte
-    ///    length regardless of how many bytes the server sends.
-    /// 3. If no length header, the reader is until server stream end.
-    ///
-    /// Note: If you use `read_to_end()` on the resulting reader, a malicious
-    /// server might return enough bytes to exhaust available memory. If you're
-    /// making requests to untrusted servers, you should use `.take()` to
-    /// limit the response bytes read.
+    /// The body has already been decoded to utf-8 when the response was received,
+    /// so the reader yields utf-8 bytes regardless of [`Response::charset()`].
     pub fn into_reader(self) -> Box<dyn Read + Send + Sync + 'static> {
-        self.0.into_reader()
+        Box::new(Cursor::new(self.body.into_bytes()))
     }
 
     /// Turn this response into a String of the response body. By default uses `utf-8`,
     /// but can work with charset, see below.
     ///
-    /// This is potentially memory inefficient for large bodies since the
-    /// implementation first reads the reader to end into a `Vec<u8>` and then
-    /// attempts to decode it using the charset.
+    /// The charset of the "Content-Type" header is applied when the response is received.
     ///
-    /// If the response is larger than 10 megabytes, this will return an error.
+    /// If the response is larger than 10 megabytes, receiving it will return an error.
     pub fn into_string(self) -> Result<String> {
-        Ok(self.0.into_string()?)
+        Ok(self.body)
     }
 
     /// Read the body of this response into a serde_json::Value, or any other type that
@@ -111,6 +172,185 @@ impl Response {
     /// You must use either a type annotation as shown below (`message: Message`), or the
     /// [turbofish operator] (`::<Type>`) so Rust knows what type you are trying to read.
     pub fn into_json<T: DeserializeOwned>(self) -> Result<T> {
-        Ok(self.0.into_json()?)
+        Ok(serde_json::from_str(&self.body)?)
+    }
+
+    /// 网关返回的trace_id，在响应头中
+    pub(crate) fn trace_id(&self) -> Option<&str> {
+        self.header("trace_id")
+            .or_else(|| self.header("alipay-trace-id"))
+    }
+
+    /// 返回网关响应中的`code`和`sub_code`
+    ///
+    /// 支付宝的响应格式为`{"xxx_response": {"code": "...", "sub_code": "..."}, "sign": "..."}`，
+    /// 出错时为`{"error_response": {...}}`，响应体不是json时返回None。
+    pub(crate) fn gateway_code(&self) -> Option<(String, String)> {
+        let value: Value = serde_json::from_str(&self.body).ok()?;
+        let content = value
+            .as_object()?
+            .iter()
+            .find(|(key, _)| key.ends_with("_response"))
+            .map(|(_, val)| val)?;
+        let code = content.get("code")?.as_str()?.to_owned();
+        let sub_code = content
+            .get("sub_code")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_owned();
+        Some((code, sub_code))
+    }
+
+    /// 解析`xxx_response`中的业务响应，method为接口名称
+    ///
+    /// 网关返回`error_response`时也会被解析，由调用方通过code判断是否成功。
+    pub(crate) fn biz_response<T: DeserializeOwned>(&self, method: &str) -> Result<BizResponse<T>> {
+        let key = format!("{}_response", method.replace('.', "_"));
+        let mut value: Value = serde_json::from_str(&self.body)?;
+        let content = value
+            .get_mut(&key)
+            .map(Value::take)
+            .or_else(|| value.get_mut("error_response").map(Value::take))
+            .ok_or_else(|| AlipayError::new(format!("{} not found in response", key)))?;
+        Ok(serde_json::from_value(content)?)
+    }
+}
+
+/// 没有业务字段的响应
+#[derive(Debug, Deserialize)]
+pub(crate) struct Empty {}
+
+/// 网关响应中`xxx_response`的内容，data是接口的业务字段
+///
+/// 先解析code、msg、sub_code和sub_msg，只有code为10000时才解析data，
+/// 所以出错时缺少业务字段不会导致解析失败。
+#[derive(Debug)]
+pub(crate) struct BizResponse<T> {
+    pub code: String,
+    pub msg: String,
+    pub sub_code: String,
+    pub sub_msg: String,
+    pub data: Option<T>,
+}
+
+#[derive(Deserialize)]
+struct Envelope {
+    code: String,
+    #[serde(default)]
+    msg: String,
+    #[serde(default)]
+    sub_code: String,
+    #[serde(default)]
+    sub_msg: String,
+}
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for BizResponse<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> StdResult<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        let envelope = Envelope::deserialize(&value).map_err(de::Error::custom)?;
+        let data = if envelope.code == SUCCESS {
+            Some(serde_json::from_value(value).map_err(de::Error::custom)?)
+        } else {
+            None
+        };
+        Ok(BizResponse {
+            code: envelope.code,
+            msg: envelope.msg,
+            sub_code: envelope.sub_code,
+            sub_msg: envelope.sub_msg,
+            data,
+        })
+    }
+}
+
+/// 接口调用成功的code
+const SUCCESS: &str = "10000";
+
+impl<T> BizResponse<T> {
+    pub fn is_success(&self) -> bool {
+        self.code == SUCCESS
+    }
+    /// code不是10000时返回业务错误，可以通过AlipayError::biz_error获取错误码
+    pub fn into_result(self) -> Result<T> {
+        match self.data {
+            Some(data) => Ok(data),
+            None => Err(self.error()),
+        }
+    }
+    pub fn error(&self) -> AlipayError {
+        BizError {
+            code: self.code.clone(),
+            msg: self.msg.clone(),
+            sub_code: self.sub_code.clone(),
+            sub_msg: self.sub_msg.clone(),
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trade::RefundResponse;
+
+    fn response(body: &str) -> Response {
+        Response::new(UreqResponse::new(200, "OK", body).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn success_payload() {
+        let res = response(
+            r#"{"alipay_trade_refund_response":{"code":"10000","msg":"Success","trade_no":"2013112011001004330000121536","out_trade_no":"6823789339978248","buyer_logon_id":"159****5620","fund_change":"Y","refund_fee":"88.88"},"sign":"x"}"#,
+        );
+        let biz = res
+            .biz_response::<RefundResponse>("alipay.trade.refund")
+            .unwrap();
+        assert!(biz.is_success());
+        let refund = biz.into_result().unwrap();
+        assert_eq!(refund.refund_fee.to_string(), "88.88");
+    }
+
+    #[test]
+    fn failure_payload_without_business_fields() {
+        let res = response(
+            r#"{"alipay_trade_refund_response":{"code":"40004","msg":"Business Failed","sub_code":"ACQ.TRADE_NOT_EXIST","sub_msg":"交易不存在"},"sign":"x"}"#,
+        );
+        let biz = res
+            .biz_response::<RefundResponse>("alipay.trade.refund")
+            .unwrap();
+        assert!(!biz.is_success());
+        assert_eq!(biz.sub_code, "ACQ.TRADE_NOT_EXIST");
+        let err = biz.into_result().unwrap_err();
+        let biz_error = err.biz_error().unwrap();
+        assert_eq!(biz_error.code, "40004");
+        assert_eq!(biz_error.sub_code, "ACQ.TRADE_NOT_EXIST");
+        assert_eq!(biz_error.sub_msg, "交易不存在");
+    }
+
+    #[test]
+    fn error_response_payload() {
+        let res = response(
+            r#"{"error_response":{"code":"40002","msg":"Invalid Arguments","sub_code":"isv.invalid-app-id","sub_msg":"无效的AppID参数"}}"#,
+        );
+        let err = res
+            .biz_response::<RefundResponse>("alipay.trade.refund")
+            .unwrap()
+            .into_result()
+            .unwrap_err();
+        assert_eq!(err.biz_error().unwrap().sub_code, "isv.invalid-app-id");
+    }
+
+    #[test]
+    fn malformed_success_payload() {
+        let res = response(
+            r#"{"alipay_trade_refund_response":{"code":"10000","msg":"Success"},"sign":"x"}"#,
+        );
+        assert!(res
+            .biz_response::<RefundResponse>("alipay.trade.refund")
+            .is_err());
+        let res = response(r#"{"sign":"x"}"#);
+        assert!(res
+            .biz_response::<RefundResponse>("alipay.trade.refund")
+            .is_err());
     }
 }
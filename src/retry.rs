@@ -0,0 +1,163 @@
+use serde_json::Value;
+use std::time::Duration;
+
+/// 重试策略
+///
+/// 只有幂等的请求才会被重试：接口名以`query`结尾（如alipay.trade.query），
+/// 或者biz_content中带有`out_trade_no`/`out_biz_no`/`out_request_no`，
+/// 支付宝会根据这些商户单号做幂等处理。
+///
+/// 可重试的失败包括网络层错误，以及网关返回的系统错误
+/// （code为20000，sub_code为`aop.*`或`isp.unknow-error`）。
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// let client = alipay_rs::Client::builder()
+///     .app_id("20210xxxxxxxxxxx")
+///     .timeout(Duration::from_secs(10))
+///     .retry_policy(alipay_rs::RetryPolicy::new(3).initial_backoff(Duration::from_millis(200)))
+///     .finish();
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+pub(crate) const IDEMPOTENT_KEYS: [&str; 3] = ["out_trade_no", "out_biz_no", "out_request_no"];
+
+impl RetryPolicy {
+    /// max_retries: 最多重试次数，不包括第一次请求
+    pub fn new(max_retries: u32) -> Self {
+        RetryPolicy {
+            max_retries,
+            ..Default::default()
+        }
+    }
+    /// 不重试
+    pub fn none() -> Self {
+        RetryPolicy::new(0)
+    }
+    /// 第一次重试前的等待时间，之后每次翻倍
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+    /// 等待时间的上限
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+
+    /// 返回这次调用最多可以重试的次数
+    pub(crate) fn retries_for(&self, method: &str, biz_content: Option<&str>) -> u32 {
+        if self.max_retries > 0 && Self::is_idempotent(method, biz_content) {
+            self.max_retries
+        } else {
+            0
+        }
+    }
+
+    fn is_idempotent(method: &str, biz_content: Option<&str>) -> bool {
+        if method.ends_with("query") {
+            return true;
+        }
+        let content = match biz_content.and_then(|v| serde_json::from_str::<Value>(v).ok()) {
+            Some(content) => content,
+            None => return false,
+        };
+        IDEMPOTENT_KEYS.iter().any(|key| match content.get(key) {
+            Some(Value::String(v)) => !v.is_empty(),
+            Some(Value::Null) | None => false,
+            Some(_) => true,
+        })
+    }
+
+    pub(crate) fn is_system_failure(code: &str, sub_code: &str) -> bool {
+        code == "20000" && (sub_code.starts_with("aop.") || sub_code == "isp.unknow-error")
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_idempotent_methods_are_never_retried() {
+        let policy = RetryPolicy::new(3);
+        for method in [
+            "alipay.trade.pay",
+            "alipay.trade.create",
+            "alipay.trade.refund",
+        ] {
+            assert_eq!(policy.retries_for(method, None), 0);
+            assert_eq!(
+                policy.retries_for(method, Some(r#"{"subject":"iphone"}"#)),
+                0
+            );
+            assert_eq!(
+                policy.retries_for(method, Some(r#"{"out_trade_no":""}"#)),
+                0
+            );
+        }
+    }
+
+    #[test]
+    fn idempotent_requests_are_retried() {
+        let policy = RetryPolicy::new(3);
+        assert_eq!(policy.retries_for("alipay.trade.query", None), 3);
+        assert_eq!(
+            policy.retries_for("alipay.trade.pay", Some(r#"{"out_trade_no":"1"}"#)),
+            3
+        );
+        assert_eq!(
+            policy.retries_for("alipay.trade.refund", Some(r#"{"out_request_no":"1"}"#)),
+            3
+        );
+        assert_eq!(
+            RetryPolicy::none().retries_for("alipay.trade.query", None),
+            0
+        );
+    }
+
+    #[test]
+    fn system_failures() {
+        assert!(RetryPolicy::is_system_failure("20000", "aop.unknow-error"));
+        assert!(RetryPolicy::is_system_failure("20000", "isp.unknow-error"));
+        assert!(!RetryPolicy::is_system_failure(
+            "40004",
+            "ACQ.TRADE_NOT_EXIST"
+        ));
+        assert!(!RetryPolicy::is_system_failure("10000", ""));
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let policy = RetryPolicy::new(5)
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(300));
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(2), Duration::from_millis(300));
+        assert_eq!(policy.backoff(40), Duration::from_millis(300));
+    }
+}
@@ -0,0 +1,522 @@
+//! 分账相关的接口，分账关系绑定、结算和结算查询
+
+use crate::{
+    error::{AlipayError, AlipayResult},
+    notify, util, AlipayParams, Cli, Money, Sign,
+};
+use futures::future::{BoxFuture, FutureExt};
+use serde::Deserialize;
+
+/// 分账账号的类型
+#[derive(AlipayParams, Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[alipay(by_ref, rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub enum AccountType {
+    /// 支付宝用户id，2088开头
+    UserId,
+    /// 支付宝登录号
+    LoginName,
+    OpenId,
+}
+
+/// 分账接收方
+#[derive(AlipayParams, Debug, Clone, Deserialize)]
+#[alipay(by_ref)]
+pub struct RoyaltyReceiver {
+    #[alipay(rename = "type")]
+    #[serde(rename = "type")]
+    pub account_type: AccountType,
+    #[alipay(required, max_len = 100)]
+    pub account: String,
+    /// 账号类型为loginName时必须填写，需要和支付宝账号的实名一致
+    #[alipay(skip_if_none)]
+    #[serde(default)]
+    pub name: Option<String>,
+    #[alipay(skip_if_none, max_len = 200)]
+    #[serde(default)]
+    pub memo: Option<String>,
+}
+
+impl RoyaltyReceiver {
+    pub fn new(account_type: AccountType, account: &str) -> Self {
+        RoyaltyReceiver {
+            account_type,
+            account: account.to_owned(),
+            name: None,
+            memo: None,
+        }
+    }
+}
+
+/// 绑定、解绑分账关系，alipay.trade.royalty.relation.bind/unbind
+#[derive(AlipayParams, Debug, Clone)]
+#[alipay(by_ref)]
+pub struct RelationRequest {
+    #[alipay(required, max_len = 20)]
+    pub receiver_list: Vec<RoyaltyReceiver>,
+    #[alipay(required, max_len = 64)]
+    pub out_request_no: String,
+}
+
+impl RelationRequest {
+    /// 创建请求，自动生成out_request_no
+    pub fn new(receiver_list: Vec<RoyaltyReceiver>) -> Self {
+        RelationRequest {
+            receiver_list,
+            out_request_no: util::request_no(),
+        }
+    }
+}
+
+/// 分页查询分账关系，alipay.trade.royalty.relation.batchquery
+#[derive(AlipayParams, Debug, Clone)]
+#[alipay(by_ref)]
+pub struct RelationQuery {
+    #[alipay(range(min = 1))]
+    pub page_num: u32,
+    #[alipay(range(min = 1, max = 100))]
+    pub page_size: u32,
+    #[alipay(required, max_len = 64)]
+    pub out_request_no: String,
+}
+
+impl RelationQuery {
+    pub fn new(page_num: u32, page_size: u32) -> Self {
+        RelationQuery {
+            page_num,
+            page_size,
+            out_request_no: util::request_no(),
+        }
+    }
+}
+
+/// 分账关系分页
+#[derive(Debug, Clone, Deserialize)]
+pub struct RelationPage {
+    #[serde(default)]
+    pub receiver_list: Vec<RoyaltyReceiver>,
+    #[serde(default)]
+    pub total_page_num: Option<u32>,
+    #[serde(default)]
+    pub total_record_num: Option<u32>,
+    #[serde(default)]
+    pub current_page_num: Option<u32>,
+    #[serde(default)]
+    pub current_page_size: Option<u32>,
+}
+
+/// 分账明细
+///
+/// amount是精确到分的金额，解析时检查小数位数，发送前检查是否在[0.01, 100000000.00]之间。
+#[derive(AlipayParams, Debug, Clone)]
+#[alipay(by_ref)]
+pub struct RoyaltyParameter {
+    #[alipay(skip_if_none)]
+    pub trans_out: Option<String>,
+    #[alipay(skip_if_none)]
+    pub trans_out_type: Option<AccountType>,
+    pub trans_in_type: AccountType,
+    #[alipay(required, max_len = 100)]
+    pub trans_in: String,
+    #[alipay(skip_if_none)]
+    pub trans_in_name: Option<String>,
+    pub amount: Money,
+    #[alipay(skip_if_none, max_len = 1000)]
+    pub desc: Option<String>,
+    #[alipay(skip_if_none)]
+    pub royalty_scene: Option<String>,
+}
+
+impl RoyaltyParameter {
+    /// 分账给接收方，转出方默认为卖家
+    pub fn new(trans_in_type: AccountType, trans_in: &str, amount: Money) -> Self {
+        RoyaltyParameter {
+            trans_out: None,
+            trans_out_type: None,
+            trans_in_type,
+            trans_in: trans_in.to_owned(),
+            trans_in_name: None,
+            amount,
+            desc: None,
+            royalty_scene: None,
+        }
+    }
+}
+
+/// 结算的扩展参数
+#[derive(AlipayParams, Debug, Clone, Default)]
+#[alipay(by_ref)]
+pub struct SettleExtendParams {
+    /// 为true时本次分账后完结，剩余的冻结资金解冻给卖家
+    #[alipay(skip_if_none)]
+    pub royalty_finish: Option<bool>,
+}
+
+/// 交易结算（分账），alipay.trade.order.settle
+///
+/// 同一笔交易可以多次分账，每次的out_request_no需要不同；
+/// 重试同一次分账时需要使用相同的out_request_no。
+#[derive(AlipayParams, Debug, Clone)]
+#[alipay(by_ref)]
+pub struct SettleRequest {
+    #[alipay(required, max_len = 64)]
+    pub out_request_no: String,
+    #[alipay(required, max_len = 64)]
+    pub trade_no: String,
+    #[alipay(required, max_len = 20)]
+    pub royalty_parameters: Vec<RoyaltyParameter>,
+    #[alipay(skip_if_none)]
+    pub extend_params: Option<SettleExtendParams>,
+    /// async时结果通过异步通知返回
+    #[alipay(skip_if_none)]
+    pub royalty_mode: Option<String>,
+}
+
+impl SettleRequest {
+    /// 创建结算请求，自动生成out_request_no
+    pub fn new(trade_no: &str, royalty_parameters: Vec<RoyaltyParameter>) -> Self {
+        SettleRequest {
+            out_request_no: util::request_no(),
+            trade_no: trade_no.to_owned(),
+            royalty_parameters,
+            extend_params: None,
+            royalty_mode: None,
+        }
+    }
+    /// 本次分账的总金额，溢出时返回错误
+    pub fn total_amount(&self) -> AlipayResult<Money> {
+        let mut params = self.royalty_parameters.iter();
+        let first = params
+            .next()
+            .ok_or_else(|| AlipayError::new("royalty_parameters is empty"))?
+            .amount;
+        params.try_fold(first, |total, param| {
+            total
+                .checked_add(param.amount)
+                .ok_or_else(|| AlipayError::new("royalty amount out of range"))
+        })
+    }
+}
+
+/// 结算结果
+#[derive(Debug, Clone, Deserialize)]
+pub struct SettleResponse {
+    pub trade_no: String,
+    #[serde(default)]
+    pub settle_no: Option<String>,
+}
+
+/// 查询结算，支付宝结算号和商户请求号二选一，alipay.trade.order.settle.query
+#[derive(AlipayParams, Debug, Clone, Default, PartialEq, Eq)]
+#[alipay(by_ref)]
+pub struct SettleQuery {
+    #[alipay(skip_if_none)]
+    pub settle_no: Option<String>,
+    #[alipay(skip_if_none)]
+    pub out_request_no: Option<String>,
+    #[alipay(skip_if_none)]
+    pub trade_no: Option<String>,
+}
+
+impl SettleQuery {
+    pub fn settle_no(settle_no: &str) -> Self {
+        SettleQuery {
+            settle_no: Some(settle_no.to_owned()),
+            ..Default::default()
+        }
+    }
+    /// 通过商户请求号查询时需要支付宝交易号
+    pub fn out_request_no(out_request_no: &str, trade_no: &str) -> Self {
+        SettleQuery {
+            settle_no: None,
+            out_request_no: Some(out_request_no.to_owned()),
+            trade_no: Some(trade_no.to_owned()),
+        }
+    }
+}
+
+/// 分账明细的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RoyaltyState {
+    Processing,
+    Success,
+    Fail,
+    #[serde(other)]
+    Unknown,
+}
+
+/// 分账执行明细
+#[derive(Debug, Clone, Deserialize)]
+pub struct RoyaltyDetail {
+    #[serde(default)]
+    pub operation_type: Option<String>,
+    #[serde(default)]
+    pub execute_dt: Option<String>,
+    #[serde(default)]
+    pub trans_out: Option<String>,
+    #[serde(default)]
+    pub trans_out_type: Option<AccountType>,
+    #[serde(default)]
+    pub trans_in: Option<String>,
+    #[serde(default)]
+    pub trans_in_type: Option<AccountType>,
+    pub amount: Money,
+    pub state: RoyaltyState,
+    #[serde(default)]
+    pub detail_id: Option<String>,
+    #[serde(default)]
+    pub error_code: Option<String>,
+    #[serde(default)]
+    pub error_desc: Option<String>,
+}
+
+/// 结算详情，也是结算通知中的内容
+#[derive(Debug, Clone, Deserialize)]
+pub struct SettleDetail {
+    #[serde(default)]
+    pub out_request_no: Option<String>,
+    #[serde(default)]
+    pub trade_no: Option<String>,
+    #[serde(default)]
+    pub settle_no: Option<String>,
+    #[serde(default)]
+    pub operation_dt: Option<String>,
+    #[serde(default)]
+    pub royalty_detail_list: Vec<RoyaltyDetail>,
+}
+
+impl SettleDetail {
+    /// 所有明细是否都已经完成（成功或者失败）
+    pub fn is_finished(&self) -> bool {
+        self.royalty_detail_list
+            .iter()
+            .all(|detail| detail.state != RoyaltyState::Processing)
+    }
+    /// 失败的明细
+    pub fn failures(&self) -> impl Iterator<Item = &RoyaltyDetail> {
+        self.royalty_detail_list
+            .iter()
+            .filter(|detail| detail.state == RoyaltyState::Fail)
+    }
+}
+
+#[derive(Deserialize)]
+struct SettleNotify {
+    msg_method: String,
+    biz_content: String,
+}
+
+/// 验签并解析结算通知（alipay.trade.order.settle.notify），处理成功后需要返回success
+pub fn verify_settle_notify<S: Sign + ?Sized>(
+    client: &S,
+    body: &str,
+) -> AlipayResult<SettleDetail> {
+    let notify: SettleNotify = notify::parse(client, body)?;
+    if notify.msg_method != "alipay.trade.order.settle.notify" {
+        return Err(AlipayError::new(format!(
+            "unexpected notification: {}",
+            notify.msg_method
+        )));
+    }
+    Ok(serde_json::from_str(&notify.biz_content)?)
+}
+
+/// 分账接口
+///
+/// ```rust,no_run
+/// use alipay_rs::royalty::{AccountType, RoyaltyApi, RoyaltyParameter, SettleRequest};
+///
+/// # async fn settle(client: &alipay_rs::Client) -> alipay_rs::error::AlipayResult<()> {
+/// let request = SettleRequest::new(
+///     "2014030411001007850000672009",
+///     vec![RoyaltyParameter::new(AccountType::UserId, "2088101126708402", "0.10".parse()?)],
+/// );
+/// let response = client.settle(&request).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait RoyaltyApi {
+    /// 绑定分账关系
+    fn relation_bind<'a>(&'a self, request: &'a RelationRequest)
+        -> BoxFuture<'a, AlipayResult<()>>;
+    /// 解绑分账关系
+    fn relation_unbind<'a>(
+        &'a self,
+        request: &'a RelationRequest,
+    ) -> BoxFuture<'a, AlipayResult<()>>;
+    /// 分页查询已绑定的分账关系
+    fn relation_query<'a>(
+        &'a self,
+        query: &'a RelationQuery,
+    ) -> BoxFuture<'a, AlipayResult<RelationPage>>;
+    /// 分账
+    fn settle<'a>(
+        &'a self,
+        request: &'a SettleRequest,
+    ) -> BoxFuture<'a, AlipayResult<SettleResponse>>;
+    /// 查询分账结果
+    fn settle_query<'a>(
+        &'a self,
+        query: &'a SettleQuery,
+    ) -> BoxFuture<'a, AlipayResult<SettleDetail>>;
+}
+
+impl<C> RoyaltyApi for C
+where
+    C: Cli + Sync,
+{
+    fn relation_bind<'a>(
+        &'a self,
+        request: &'a RelationRequest,
+    ) -> BoxFuture<'a, AlipayResult<()>> {
+        relation(self, "alipay.trade.royalty.relation.bind", request)
+    }
+
+    fn relation_unbind<'a>(
+        &'a self,
+        request: &'a RelationRequest,
+    ) -> BoxFuture<'a, AlipayResult<()>> {
+        relation(self, "alipay.trade.royalty.relation.unbind", request)
+    }
+
+    fn relation_query<'a>(
+        &'a self,
+        query: &'a RelationQuery,
+    ) -> BoxFuture<'a, AlipayResult<RelationPage>> {
+        async move {
+            let method = "alipay.trade.royalty.relation.batchquery";
+            let response = self.post(method, query).await?;
+            response.biz_response(method)?.into_result()
+        }
+        .boxed()
+    }
+
+    fn settle<'a>(
+        &'a self,
+        request: &'a SettleRequest,
+    ) -> BoxFuture<'a, AlipayResult<SettleResponse>> {
+        async move {
+            let method = "alipay.trade.order.settle";
+            let response = self.post(method, request).await?;
+            response.biz_response(method)?.into_result()
+        }
+        .boxed()
+    }
+
+    fn settle_query<'a>(
+        &'a self,
+        query: &'a SettleQuery,
+    ) -> BoxFuture<'a, AlipayResult<SettleDetail>> {
+        async move {
+            let method = "alipay.trade.order.settle.query";
+            let response = self.post(method, query).await?;
+            response.biz_response(method)?.into_result()
+        }
+        .boxed()
+    }
+}
+
+/// 绑定和解绑的响应中只有result_code
+#[derive(Deserialize)]
+struct RelationResponse {
+    #[serde(default)]
+    result_code: Option<String>,
+}
+
+fn relation<'a, C: Cli + Sync>(
+    client: &'a C,
+    method: &'static str,
+    request: &'a RelationRequest,
+) -> BoxFuture<'a, AlipayResult<()>> {
+    async move {
+        let response = client.post(method, request).await?;
+        let data: RelationResponse = response.biz_response(method)?.into_result()?;
+        match data.result_code.as_deref() {
+            None | Some("SUCCESS") => Ok(()),
+            Some(code) => Err(AlipayError::new(format!("{} failed: {}", method, code))),
+        }
+    }
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{client, serve_biz};
+    use futures::executor::block_on;
+
+    const SETTLE: &str = "alipay.trade.order.settle";
+    const SETTLE_QUERY: &str = "alipay.trade.order.settle.query";
+    const TRADE_NO: &str = "2014030411001007850000672009";
+
+    fn request(amount: &str) -> SettleRequest {
+        SettleRequest::new(
+            TRADE_NO,
+            vec![RoyaltyParameter::new(
+                AccountType::UserId,
+                "2088101126708402",
+                amount.parse().unwrap(),
+            )],
+        )
+    }
+
+    #[test]
+    fn settle() {
+        let (url, handle) = serve_biz(&[(
+            SETTLE,
+            r#"{"code":"10000","msg":"Success","trade_no":"2014030411001007850000672009","settle_no":"20131124001"}"#,
+        )]);
+        let response = block_on(client(&url).settle(&request("0.01"))).unwrap();
+        handle.join().unwrap();
+        assert_eq!(response.trade_no, TRADE_NO);
+        assert_eq!(response.settle_no.as_deref(), Some("20131124001"));
+    }
+
+    #[test]
+    fn settle_errors_are_biz_errors() {
+        let (url, handle) = serve_biz(&[(
+            SETTLE,
+            r#"{"code":"40004","msg":"Business Failed","sub_code":"ACQ.TRADE_SETTLE_ERROR","sub_msg":"分账处理失败"}"#,
+        )]);
+        let err = block_on(client(&url).settle(&request("0.01"))).unwrap_err();
+        handle.join().unwrap();
+        let biz = err.biz_error().unwrap();
+        assert_eq!(biz.code, "40004");
+        assert_eq!(biz.sub_code, "ACQ.TRADE_SETTLE_ERROR");
+    }
+
+    #[test]
+    fn settle_rejects_zero_amounts_before_sending() {
+        let request = request("0");
+        let err = block_on(client("http://127.0.0.1:1/gateway.do").settle(&request)).unwrap_err();
+        assert!(err.validation_error().is_some());
+        assert!(request.total_amount().unwrap().is_zero());
+    }
+
+    #[test]
+    fn settle_query_accepts_zero_amounts() {
+        let (url, handle) = serve_biz(&[
+            (
+                SETTLE_QUERY,
+                r#"{"code":"10000","msg":"Success","out_request_no":"20160727001","settle_no":"20131124001","royalty_detail_list":[{"operation_type":"transfer","trans_in":"2088101126708402","trans_in_type":"userId","amount":"0.00","state":"FAIL","error_code":"TXN_RESULT_ACCOUNT_BALANCE_NOT_ENOUGH"},{"operation_type":"transfer","trans_in":"2088101126708403","amount":"0.10","state":"SUCCESS"}]}"#,
+            ),
+            (
+                SETTLE_QUERY,
+                r#"{"code":"40004","msg":"Business Failed","sub_code":"ACQ.SETTLE_NOT_EXIST","sub_msg":"结算单不存在"}"#,
+            ),
+        ]);
+        let client = client(&url);
+        let detail = block_on(client.settle_query(&SettleQuery::settle_no("20131124001"))).unwrap();
+        assert!(detail.is_finished());
+        let failures: Vec<_> = detail.failures().collect();
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].amount.is_zero());
+        assert_eq!(failures[0].trans_in_type, Some(AccountType::UserId));
+
+        let err =
+            block_on(client.settle_query(&SettleQuery::settle_no("20131124002"))).unwrap_err();
+        assert_eq!(err.biz_error().unwrap().sub_code, "ACQ.SETTLE_NOT_EXIST");
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,115 @@
+use crate::error::AlipayResult;
+use openssl::{
+    base64,
+    pkey::{PKey, Private},
+    rsa::Rsa,
+};
+use serde::{Deserialize, Deserializer};
+use std::{borrow::Cow, fmt, sync::Arc};
+use zeroize::Zeroizing;
+
+/// 私钥等敏感内容，释放时内存会被清零，Debug只输出***
+///
+/// ```rust
+/// let secret = alipay_rs::SecretString::new("MIIEvQIBADANBg...");
+/// assert_eq!(format!("{:?}", secret), "***");
+/// ```
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretString(Zeroizing<String>);
+
+impl SecretString {
+    pub fn new<S: Into<String>>(secret: S) -> Self {
+        SecretString(Zeroizing::new(secret.into()))
+    }
+    /// 读取明文，不要写入日志
+    pub fn expose(&self) -> &str {
+        self.0.as_str()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        SecretString::new(secret)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        SecretString::new(secret)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(SecretString::new)
+    }
+}
+
+/// 外部密钥来源，比如KMS、Vault或者密钥文件
+///
+/// 每次签名时调用，实现中可以自行缓存和轮换密钥。
+///
+/// ```rust,no_run
+/// use alipay_rs::{error::AlipayResult, SecretProvider, SecretString};
+///
+/// struct FileKey(std::path::PathBuf);
+///
+/// impl SecretProvider for FileKey {
+///     fn private_key(&self) -> AlipayResult<SecretString> {
+///         Ok(SecretString::new(std::fs::read_to_string(&self.0)?.trim()))
+///     }
+/// }
+///
+/// let client = alipay_rs::Client::builder()
+///     .app_id("20210xxxxxxxxxxx")
+///     .secret_provider(FileKey("/run/secrets/alipay_private_key".into()))
+///     .finish();
+/// ```
+pub trait SecretProvider: Send + Sync {
+    /// 应用私钥，base64编码，不包含PEM的头尾
+    fn private_key(&self) -> AlipayResult<SecretString>;
+}
+
+/// 客户端持有的应用私钥
+#[derive(Clone)]
+pub(crate) enum PrivateKey {
+    Inline(SecretString),
+    Provider(Arc<dyn SecretProvider>),
+}
+
+impl PrivateKey {
+    pub(crate) fn load(&self) -> AlipayResult<PKey<Private>> {
+        let secret = match self {
+            PrivateKey::Inline(secret) => Cow::Borrowed(secret),
+            PrivateKey::Provider(provider) => Cow::Owned(provider.private_key()?),
+        };
+        let der = Zeroizing::new(base64::decode_block(secret.expose())?);
+        let rsa = Rsa::private_key_from_der(&der)?;
+
+        Ok(PKey::from_rsa(rsa)?)
+    }
+}
+
+impl Default for PrivateKey {
+    fn default() -> Self {
+        PrivateKey::Inline(SecretString::default())
+    }
+}
+
+impl fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrivateKey::Inline(_) => f.write_str("***"),
+            PrivateKey::Provider(_) => f.write_str("SecretProvider"),
+        }
+    }
+}
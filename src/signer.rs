@@ -0,0 +1,88 @@
+use crate::{error::AlipayResult, secret::PrivateKey, SecretString, Sign};
+use openssl::{
+    base64,
+    hash::MessageDigest,
+    pkey::{PKey, Public},
+    rsa::Rsa,
+    sign::{Signer, Verifier},
+};
+use std::sync::Arc;
+
+/// 客户端和ClientWithParams共享的签名实现
+pub(crate) type SharedSigner = Arc<dyn Sign + Send + Sync>;
+
+/// 默认的签名实现，使用内存中的应用私钥签名，支付宝公钥验签，RSA2（SHA256WithRSA）
+///
+/// 使用KMS/HSM签名时，可以用只有支付宝公钥的RsaSigner验签：
+///
+/// ```rust
+/// use alipay_rs::{error::AlipayResult, RsaSigner, Sign};
+///
+/// // 本地的替身，实际使用时调用KMS的签名接口
+/// struct Kms {
+///     verifier: RsaSigner,
+/// }
+///
+/// impl Sign for Kms {
+///     fn sign(&self, params: &str) -> AlipayResult<String> {
+///         Ok(format!("signed:{}", params.len()))
+///     }
+///     fn verify(&self, source: &str, signature: &str) -> AlipayResult<bool> {
+///         self.verifier.verify(source, signature)
+///     }
+/// }
+///
+/// let client = alipay_rs::Client::builder()
+///     .app_id("20210xxxxxxxxxxx")
+///     .signer(Kms {
+///         verifier: RsaSigner::new("", "MIIBIjANBg..."),
+///     })
+///     .finish();
+/// assert_eq!(client.sign("a=1").unwrap(), "signed:3");
+/// ```
+#[derive(Debug, Clone)]
+pub struct RsaSigner {
+    private_key: PrivateKey,
+    public_key: String,
+}
+
+impl RsaSigner {
+    /// private_key: 应用私钥，public_key: 支付宝公钥，都是base64编码，不包含PEM的头尾
+    pub fn new<K: Into<SecretString>>(private_key: K, public_key: &str) -> Self {
+        RsaSigner::from_key(
+            PrivateKey::Inline(private_key.into()),
+            public_key.to_owned(),
+        )
+    }
+
+    pub(crate) fn from_key(private_key: PrivateKey, public_key: String) -> Self {
+        RsaSigner {
+            private_key,
+            public_key,
+        }
+    }
+
+    fn get_public_key(&self) -> AlipayResult<PKey<Public>> {
+        let cert_content = base64::decode_block(self.public_key.as_str())?;
+        let rsa = Rsa::public_key_from_der(&cert_content)?;
+
+        Ok(PKey::from_rsa(rsa)?)
+    }
+}
+
+impl Sign for RsaSigner {
+    fn sign(&self, params: &str) -> AlipayResult<String> {
+        let private_key = self.private_key.load()?;
+        let mut signer = Signer::new(MessageDigest::sha256(), &private_key)?;
+        signer.update(params.as_bytes())?;
+        let sign = base64::encode_block(signer.sign_to_vec()?.as_ref());
+        Ok(sign)
+    }
+    fn verify(&self, source: &str, signature: &str) -> AlipayResult<bool> {
+        let public_key = self.get_public_key()?;
+        let sign = base64::decode_block(signature)?;
+        let mut verifier = Verifier::new(MessageDigest::sha256(), &public_key)?;
+        verifier.update(source.as_bytes())?;
+        Ok(verifier.verify(sign.as_slice())?)
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{
+    error::AlipayResult, AlipayRequest, Client, ClientBuilder, Interceptor, RsaSigner, Sign,
+};
+use openssl::{base64, rsa::Rsa};
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    thread::{self, JoinHandle},
+};
+
+/// 服务端收到的请求，请求头原样保存，chunked的请求体已经解码
+pub(crate) struct Captured {
+    pub head: String,
+    pub body: Vec<u8>,
+}
+
+/// 测试用的接口内容加密密钥，128位
+pub(crate) const AES_KEY: &str = "aa4BtZ4tspm2wnXLb1ThQA==";
+
+/// 在本地端口上依次回复http响应，每个连接回复一个，返回网关地址
+pub(crate) fn serve(responses: &[(&str, &str)]) -> (String, JoinHandle<Vec<Captured>>) {
+    serve_raw(
+        responses
+            .iter()
+            .map(|(status, body)| http_response(status, &[], body))
+            .collect(),
+    )
+}
+
+/// 完整的http响应，headers是除了Content-Type和Content-Length之外的响应头
+pub(crate) fn http_response(status: &str, headers: &[(&str, &str)], body: &str) -> String {
+    let headers: String = headers
+        .iter()
+        .map(|(name, val)| format!("{}: {}\r\n", name, val))
+        .collect();
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json;charset=utf-8\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        headers,
+        body
+    )
+}
+
+/// 依次回复已经生成的http响应，返回收到的请求
+pub(crate) fn serve_raw(responses: Vec<String>) -> (String, JoinHandle<Vec<Captured>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/gateway.do", listener.local_addr().unwrap());
+    let handle = thread::spawn(move || {
+        let mut requests = Vec::new();
+        for response in responses {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let request = read_request(&mut reader);
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+            requests.extend(request);
+        }
+        requests
+    });
+    (url, handle)
+}
+
+/// 读取请求头和请求体，连接提前关闭时返回None
+fn read_request(reader: &mut impl BufRead) -> Option<Captured> {
+    let mut head = String::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        head.push_str(&line);
+        if line == "\r\n" {
+            break;
+        }
+    }
+    let header = |name: &str| {
+        head.lines().find_map(|line| {
+            let (key, val) = line.split_once(':')?;
+            key.eq_ignore_ascii_case(name)
+                .then(|| val.trim().to_owned())
+        })
+    };
+    let mut body = Vec::new();
+    if header("Transfer-Encoding").is_some_and(|v| v.eq_ignore_ascii_case("chunked")) {
+        loop {
+            let mut size = String::new();
+            reader.read_line(&mut size).ok()?;
+            let size = usize::from_str_radix(size.trim(), 16).ok()?;
+            let mut chunk = vec![0u8; size + 2];
+            reader.read_exact(&mut chunk).ok()?;
+            if size == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..size]);
+        }
+    } else if let Some(len) = header("Content-Length") {
+        body.resize(len.parse().ok()?, 0);
+        reader.read_exact(&mut body).ok()?;
+    }
+    Some(Captured { head, body })
+}
+
+/// 依次回复业务响应，`(接口名, 响应内容)`
+pub(crate) fn serve_biz(responses: &[(&str, &str)]) -> (String, JoinHandle<Vec<Captured>>) {
+    let bodies: Vec<String> = responses
+        .iter()
+        .map(|(method, content)| {
+            format!(
+                r#"{{"{}_response":{},"sign":"x"}}"#,
+                method.replace('.', "_"),
+                content
+            )
+        })
+        .collect();
+    let responses: Vec<(&str, &str)> = bodies
+        .iter()
+        .map(|body| ("200 OK", body.as_str()))
+        .collect();
+    serve(&responses)
+}
+
+struct StubSigner;
+
+impl Sign for StubSigner {
+    fn sign(&self, _params: &str) -> AlipayResult<String> {
+        Ok("stub".to_owned())
+    }
+    fn verify(&self, _source: &str, _signature: &str) -> AlipayResult<bool> {
+        Ok(true)
+    }
+}
+
+struct Gateway(String);
+
+impl Interceptor for Gateway {
+    fn before_sign(&self, request: &mut AlipayRequest) -> AlipayResult<()> {
+        request.url = self.0.clone();
+        Ok(())
+    }
+}
+
+/// 请求发往url的客户端，签名为固定的字符串
+pub(crate) fn client(url: &str) -> Client {
+    builder(url).finish()
+}
+
+/// 请求发往url、签名为固定字符串的ClientBuilder，可以继续修改其他设置
+pub(crate) fn builder(url: &str) -> ClientBuilder<'static> {
+    let mut builder = Client::builder();
+    builder
+        .app_id("2021000000000000")
+        .signer(StubSigner)
+        .interceptor(Gateway(url.to_owned()));
+    builder
+}
+
+/// 新生成的RSA密钥，私钥和公钥属于同一对，签名可以通过验证
+pub(crate) fn rsa_signer() -> RsaSigner {
+    let (private_key, public_key) = rsa_keys();
+    RsaSigner::new(private_key, &public_key)
+}
+
+/// 新生成的`(私钥, 公钥)`，base64编码的DER
+pub(crate) fn rsa_keys() -> (String, String) {
+    let rsa = Rsa::generate(2048).unwrap();
+    (
+        base64::encode_block(&rsa.private_key_to_der().unwrap()),
+        base64::encode_block(&rsa.public_key_to_der().unwrap()),
+    )
+}
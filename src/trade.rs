@@ -0,0 +1,559 @@
+//! 交易相关的接口
+
+use crate::{
+    error::{AlipayError, AlipayResult},
+    util, AlipayParams, AlipayValue, Cli, Money,
+};
+use alipay_params::IndexMap;
+use futures::future::{BoxFuture, FutureExt};
+use serde::{Deserialize, Deserializer};
+use std::time::{Duration, Instant};
+
+/// 订单号，支付宝交易号和商户订单号二选一
+#[derive(AlipayParams, Debug, Clone, PartialEq, Eq)]
+#[alipay(by_ref, rename_all = "snake_case")]
+pub enum TradeId {
+    /// 支付宝交易号
+    TradeNo(String),
+    /// 商户订单号
+    OutTradeNo(String),
+}
+
+/// 退款请求，alipay.trade.refund
+///
+/// 同一笔交易可以多次部分退款，每次退款的out_request_no需要不同；
+/// 重试同一次退款时需要使用相同的out_request_no，否则会重复退款。
+#[derive(AlipayParams, Debug, Clone)]
+#[alipay(by_ref)]
+pub struct RefundRequest {
+    #[alipay(flatten)]
+    pub trade: TradeId,
+    pub refund_amount: Money,
+    #[alipay(skip_if_none, max_len = 256)]
+    pub refund_reason: Option<String>,
+    #[alipay(required, max_len = 64)]
+    pub out_request_no: String,
+}
+
+impl RefundRequest {
+    /// 创建退款请求，自动生成out_request_no
+    pub fn new(trade: TradeId, refund_amount: Money) -> Self {
+        RefundRequest {
+            trade,
+            refund_amount,
+            refund_reason: None,
+            out_request_no: util::request_no(),
+        }
+    }
+}
+
+/// 退款结果
+#[derive(Debug, Clone, Deserialize)]
+pub struct RefundResponse {
+    pub trade_no: String,
+    pub out_trade_no: String,
+    #[serde(default)]
+    pub buyer_logon_id: Option<String>,
+    /// 本次退款是否发生了资金变化
+    ///
+    /// 为false时可能是该out_request_no已经退款过，也可能是退款还在处理中，需要通过refund_query确认。
+    #[serde(deserialize_with = "deserialize_yes_no")]
+    pub fund_change: bool,
+    /// 交易累计退款金额
+    pub refund_fee: Money,
+}
+
+/// 退款查询中的退款信息，alipay.trade.fastpay.refund.query
+#[derive(Debug, Clone, Deserialize)]
+pub struct RefundDetail {
+    #[serde(default)]
+    pub trade_no: Option<String>,
+    #[serde(default)]
+    pub out_trade_no: Option<String>,
+    #[serde(default)]
+    pub out_request_no: Option<String>,
+    /// 交易金额
+    #[serde(default)]
+    pub total_amount: Option<Money>,
+    /// 本次退款的金额
+    #[serde(default)]
+    pub refund_amount: Option<Money>,
+    #[serde(default)]
+    pub refund_status: Option<String>,
+    /// 退款时间
+    #[serde(default)]
+    pub gmt_refund_pay: Option<String>,
+}
+
+/// 退款查询结果
+#[derive(Debug, Clone)]
+pub enum RefundStatus {
+    /// 退款成功
+    Succeeded(RefundDetail),
+    /// 查询到了退款，但还没有成功，可能在处理中或者失败，
+    /// 可以稍后再查询或者使用相同的out_request_no重新退款
+    Pending(RefundDetail),
+    /// 没有查询到退款，退款请求没有被受理或者交易不存在
+    NotFound,
+}
+
+#[derive(AlipayParams)]
+struct RefundQuery<'a> {
+    #[alipay(flatten)]
+    trade: &'a TradeId,
+    out_request_no: &'a str,
+    query_options: [&'a str; 1],
+}
+
+/// 当面付条码支付请求，alipay.trade.pay
+#[derive(AlipayParams, Debug, Clone)]
+#[alipay(by_ref)]
+pub struct PayRequest {
+    #[alipay(required, max_len = 64)]
+    pub out_trade_no: String,
+    /// 默认为bar_code
+    pub scene: String,
+    #[alipay(required, max_len = 32)]
+    pub auth_code: String,
+    #[alipay(required, max_len = 256)]
+    pub subject: String,
+    pub total_amount: Money,
+    /// 其他参数，比如store_id、timeout_express
+    #[alipay(flatten)]
+    pub extend: IndexMap<String, AlipayValue>,
+}
+
+impl PayRequest {
+    pub fn new<S: Into<String>>(
+        out_trade_no: S,
+        auth_code: S,
+        subject: S,
+        total_amount: Money,
+    ) -> Self {
+        PayRequest {
+            out_trade_no: out_trade_no.into(),
+            scene: "bar_code".to_owned(),
+            auth_code: auth_code.into(),
+            subject: subject.into(),
+            total_amount,
+            extend: IndexMap::new(),
+        }
+    }
+}
+
+/// 交易状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TradeStatus {
+    /// 交易创建，等待买家付款
+    WaitBuyerPay,
+    /// 未付款交易超时关闭，或支付完成后全额退款
+    TradeClosed,
+    /// 交易支付成功
+    TradeSuccess,
+    /// 交易结束，不可退款
+    TradeFinished,
+    #[serde(other)]
+    Unknown,
+}
+
+/// 交易信息，alipay.trade.pay和alipay.trade.query的响应
+#[derive(Debug, Clone, Deserialize)]
+pub struct TradeDetail {
+    #[serde(default)]
+    pub trade_no: Option<String>,
+    #[serde(default)]
+    pub out_trade_no: Option<String>,
+    #[serde(default)]
+    pub buyer_logon_id: Option<String>,
+    #[serde(default)]
+    pub buyer_user_id: Option<String>,
+    /// alipay.trade.pay的响应中没有交易状态
+    #[serde(default)]
+    pub trade_status: Option<TradeStatus>,
+    #[serde(default)]
+    pub total_amount: Option<Money>,
+    #[serde(default)]
+    pub receipt_amount: Option<Money>,
+    #[serde(default)]
+    pub gmt_payment: Option<String>,
+}
+
+/// 撤销结果，alipay.trade.cancel
+#[derive(Debug, Clone, Deserialize)]
+pub struct CancelResponse {
+    #[serde(default)]
+    pub trade_no: Option<String>,
+    #[serde(default)]
+    pub out_trade_no: Option<String>,
+    /// 是否需要重试
+    #[serde(default, deserialize_with = "deserialize_yes_no")]
+    pub retry_flag: bool,
+    /// 撤销触发的动作，close：关闭交易，refund：产生了退款
+    #[serde(default)]
+    pub action: Option<String>,
+}
+
+/// 轮询设置，默认每5秒查询一次，30秒后撤销交易
+#[derive(Debug, Clone)]
+pub struct PollOptions {
+    /// 查询间隔
+    pub interval: Duration,
+    /// 从发起支付开始计算，超过后撤销交易；撤销前至少会查询一次
+    pub deadline: Duration,
+    /// 撤销失败或者返回retry_flag为Y时的最大重试次数
+    pub max_cancel_retries: u32,
+}
+
+impl Default for PollOptions {
+    fn default() -> Self {
+        PollOptions {
+            interval: Duration::from_secs(5),
+            deadline: Duration::from_secs(30),
+            max_cancel_retries: 3,
+        }
+    }
+}
+
+/// 条码支付的最终结果
+#[derive(Debug, Clone)]
+pub enum PayOutcome {
+    /// 支付成功
+    Paid(TradeDetail),
+    /// 交易已关闭
+    Closed(TradeDetail),
+    /// 等待超时，交易已被撤销
+    Cancelled(CancelResponse),
+}
+
+/// 交易接口，为所有实现了Cli的客户端实现
+///
+/// ```rust,no_run
+/// use alipay_rs::trade::{RefundRequest, RefundStatus, TradeApi, TradeId};
+///
+/// # async fn refund(client: &alipay_rs::Client) -> alipay_rs::error::AlipayResult<()> {
+/// let trade = TradeId::OutTradeNo("20150320010101001".to_owned());
+/// let mut request = RefundRequest::new(trade.clone(), "0.01".parse()?);
+/// request.refund_reason = Some("正常退款".to_owned());
+/// let response = client.refund(&request).await?;
+/// if !response.fund_change {
+///     match client.refund_query(&trade, &request.out_request_no).await? {
+///         RefundStatus::Succeeded(detail) => println!("{:?}", detail),
+///         RefundStatus::Pending(_) => println!("退款处理中"),
+///         RefundStatus::NotFound => println!("退款不存在"),
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub trait TradeApi {
+    /// 退款，alipay.trade.refund
+    fn refund<'a>(
+        &'a self,
+        request: &'a RefundRequest,
+    ) -> BoxFuture<'a, AlipayResult<RefundResponse>>;
+    /// 查询退款，alipay.trade.fastpay.refund.query
+    fn refund_query<'a>(
+        &'a self,
+        trade: &'a TradeId,
+        out_request_no: &'a str,
+    ) -> BoxFuture<'a, AlipayResult<RefundStatus>>;
+    /// 查询交易，alipay.trade.query
+    fn query<'a>(&'a self, trade: &'a TradeId) -> BoxFuture<'a, AlipayResult<TradeDetail>>;
+    /// 撤销交易，alipay.trade.cancel
+    fn cancel<'a>(&'a self, trade: &'a TradeId) -> BoxFuture<'a, AlipayResult<CancelResponse>>;
+    /// 条码支付并等待结果
+    ///
+    /// 支付返回10003（等待用户付款）或者结果未知时，按照options中的间隔调用alipay.trade.query，
+    /// 直到交易成功或关闭；超过deadline后调用alipay.trade.cancel撤销交易。
+    /// 查询间隔的等待不会阻塞线程，可以在任何异步运行时中使用。
+    ///
+    /// 网络错误、系统繁忙和交易不存在（交易可能还没创建）时继续查询，其他错误直接返回。
+    fn pay_and_wait<'a>(
+        &'a self,
+        request: &'a PayRequest,
+        options: PollOptions,
+    ) -> BoxFuture<'a, AlipayResult<PayOutcome>>;
+}
+
+impl<C> TradeApi for C
+where
+    C: Cli + Sync,
+{
+    fn refund<'a>(
+        &'a self,
+        request: &'a RefundRequest,
+    ) -> BoxFuture<'a, AlipayResult<RefundResponse>> {
+        async move {
+            let method = "alipay.trade.refund";
+            let response = self.post(method, request).await?;
+            response.biz_response(method)?.into_result()
+        }
+        .boxed()
+    }
+
+    fn refund_query<'a>(
+        &'a self,
+        trade: &'a TradeId,
+        out_request_no: &'a str,
+    ) -> BoxFuture<'a, AlipayResult<RefundStatus>> {
+        async move {
+            let method = "alipay.trade.fastpay.refund.query";
+            let query = RefundQuery {
+                trade,
+                out_request_no,
+                query_options: ["gmt_refund_pay"],
+            };
+            let response = self.post(method, query).await?;
+            let biz = response.biz_response::<RefundDetail>(method)?;
+            if !biz.is_success() {
+                return if biz.sub_code == "ACQ.TRADE_NOT_EXIST" {
+                    Ok(RefundStatus::NotFound)
+                } else {
+                    Err(biz.error())
+                };
+            }
+            let detail = biz.into_result()?;
+            Ok(
+                if detail.refund_status.as_deref() == Some("REFUND_SUCCESS") {
+                    RefundStatus::Succeeded(detail)
+                } else if detail.refund_amount.is_some() {
+                    RefundStatus::Pending(detail)
+                } else {
+                    RefundStatus::NotFound
+                },
+            )
+        }
+        .boxed()
+    }
+
+    fn query<'a>(&'a self, trade: &'a TradeId) -> BoxFuture<'a, AlipayResult<TradeDetail>> {
+        async move {
+            let method = "alipay.trade.query";
+            let response = self.post(method, trade).await?;
+            response.biz_response(method)?.into_result()
+        }
+        .boxed()
+    }
+
+    fn cancel<'a>(&'a self, trade: &'a TradeId) -> BoxFuture<'a, AlipayResult<CancelResponse>> {
+        async move {
+            let method = "alipay.trade.cancel";
+            let response = self.post(method, trade).await?;
+            response.biz_response(method)?.into_result()
+        }
+        .boxed()
+    }
+
+    fn pay_and_wait<'a>(
+        &'a self,
+        request: &'a PayRequest,
+        options: PollOptions,
+    ) -> BoxFuture<'a, AlipayResult<PayOutcome>> {
+        async move {
+            let start = Instant::now();
+            let method = "alipay.trade.pay";
+            // 网络错误和系统错误（20000）时结果未知，和10003一样需要查询
+            let response = self.post(method, request).await;
+            match response.and_then(|r| r.biz_response::<TradeDetail>(method)) {
+                Ok(biz) => match biz.code.as_str() {
+                    "10000" => return biz.into_result().map(PayOutcome::Paid),
+                    "10003" | "20000" => {}
+                    _ => return Err(biz.error()),
+                },
+                Err(err) if err.is_transport_error() => {}
+                Err(err) => return Err(err),
+            }
+
+            let trade = TradeId::OutTradeNo(request.out_trade_no.clone());
+            // 至少查询一次再决定是否撤销，查询间隔不超过剩余时间
+            loop {
+                let remaining = options.deadline.saturating_sub(start.elapsed());
+                util::sleep(options.interval.min(remaining)).await;
+                match self.query(&trade).await {
+                    Ok(detail) => match detail.trade_status {
+                        Some(TradeStatus::TradeSuccess) | Some(TradeStatus::TradeFinished) => {
+                            return Ok(PayOutcome::Paid(detail))
+                        }
+                        Some(TradeStatus::TradeClosed) => return Ok(PayOutcome::Closed(detail)),
+                        _ => {}
+                    },
+                    Err(err) if is_transient(&err) => {}
+                    Err(err) => return Err(err),
+                }
+                if start.elapsed() + options.interval >= options.deadline {
+                    break;
+                }
+            }
+
+            let mut retries = 0;
+            loop {
+                match self.cancel(&trade).await {
+                    Ok(response) if !response.retry_flag => {
+                        return Ok(PayOutcome::Cancelled(response))
+                    }
+                    Ok(_) if retries < options.max_cancel_retries => {}
+                    Err(err) if retries < options.max_cancel_retries && is_transient(&err) => {}
+                    Ok(_) => {
+                        return Err(AlipayError::new(format!(
+                            "cancel {} did not finish after {} retries",
+                            request.out_trade_no, retries
+                        )))
+                    }
+                    Err(err) => return Err(err),
+                }
+                retries += 1;
+                util::sleep(options.interval).await;
+            }
+        }
+        .boxed()
+    }
+}
+
+/// 可以继续轮询的错误：网络错误、系统繁忙，以及交易还没有创建
+fn is_transient(err: &AlipayError) -> bool {
+    match err.biz_error() {
+        Some(biz) => {
+            biz.code == "20000"
+                || matches!(
+                    biz.sub_code.as_str(),
+                    "ACQ.TRADE_NOT_EXIST" | "ACQ.SYSTEM_ERROR"
+                )
+        }
+        None => err.is_transport_error(),
+    }
+}
+
+/// 支付宝中的"Y"和"N"
+fn deserialize_yes_no<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    Ok(String::deserialize(deserializer)? == "Y")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{client, serve_biz};
+    use futures::executor::block_on;
+
+    const PAY: &str = "alipay.trade.pay";
+    const QUERY: &str = "alipay.trade.query";
+    const WAITING: &str = r#"{"code":"10003","msg":" order success pay inprocess","trade_no":"2013112011001004330000121536","out_trade_no":"6823789339978248"}"#;
+
+    fn options() -> PollOptions {
+        PollOptions {
+            interval: Duration::from_millis(1),
+            deadline: Duration::from_secs(5),
+            max_cancel_retries: 0,
+        }
+    }
+
+    fn request() -> PayRequest {
+        PayRequest::new(
+            "6823789339978248",
+            "28763443825664394",
+            "iphone",
+            "88.88".parse().unwrap(),
+        )
+    }
+
+    #[test]
+    fn polls_until_paid() {
+        let (url, handle) = serve_biz(&[
+            (PAY, WAITING),
+            (
+                QUERY,
+                r#"{"code":"40004","msg":"Business Failed","sub_code":"ACQ.TRADE_NOT_EXIST","sub_msg":"交易不存在"}"#,
+            ),
+            (
+                QUERY,
+                r#"{"code":"10000","msg":"Success","trade_no":"2013112011001004330000121536","out_trade_no":"6823789339978248","trade_status":"TRADE_SUCCESS","total_amount":"88.88"}"#,
+            ),
+        ]);
+        let outcome = block_on(client(&url).pay_and_wait(&request(), options())).unwrap();
+        handle.join().unwrap();
+        match outcome {
+            PayOutcome::Paid(detail) => {
+                assert_eq!(detail.total_amount, Some("88.88".parse().unwrap()))
+            }
+            outcome => panic!("unexpected outcome: {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn queries_once_before_cancelling_when_interval_exceeds_deadline() {
+        let (url, handle) = serve_biz(&[
+            (PAY, WAITING),
+            (
+                QUERY,
+                r#"{"code":"10000","msg":"Success","trade_no":"2013112011001004330000121536","out_trade_no":"6823789339978248","trade_status":"WAIT_BUYER_PAY","total_amount":"88.88"}"#,
+            ),
+            (
+                "alipay.trade.cancel",
+                r#"{"code":"10000","msg":"Success","trade_no":"2013112011001004330000121536","out_trade_no":"6823789339978248","retry_flag":"N","action":"close"}"#,
+            ),
+        ]);
+        let options = PollOptions {
+            interval: Duration::from_secs(60),
+            deadline: Duration::from_millis(1),
+            max_cancel_retries: 0,
+        };
+        let outcome = block_on(client(&url).pay_and_wait(&request(), options)).unwrap();
+        handle.join().unwrap();
+        match outcome {
+            PayOutcome::Cancelled(response) => {
+                assert_eq!(response.action.as_deref(), Some("close"))
+            }
+            outcome => panic!("unexpected outcome: {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn paid_on_the_only_query_is_not_cancelled() {
+        let (url, handle) = serve_biz(&[
+            (PAY, WAITING),
+            (
+                QUERY,
+                r#"{"code":"10000","msg":"Success","trade_no":"2013112011001004330000121536","out_trade_no":"6823789339978248","trade_status":"TRADE_SUCCESS","total_amount":"88.88"}"#,
+            ),
+        ]);
+        let options = PollOptions {
+            interval: Duration::from_secs(60),
+            deadline: Duration::from_millis(1),
+            max_cancel_retries: 0,
+        };
+        let outcome = block_on(client(&url).pay_and_wait(&request(), options)).unwrap();
+        handle.join().unwrap();
+        assert!(matches!(outcome, PayOutcome::Paid(_)));
+    }
+
+    #[test]
+    fn returns_persistent_query_errors() {
+        let (url, handle) = serve_biz(&[
+            (PAY, WAITING),
+            (
+                QUERY,
+                r#"{"code":"40006","msg":"Insufficient Permissions","sub_code":"isv.insufficient-isv-permissions","sub_msg":"ISV权限不足"}"#,
+            ),
+        ]);
+        let err = block_on(client(&url).pay_and_wait(&request(), options())).unwrap_err();
+        handle.join().unwrap();
+        assert_eq!(
+            err.biz_error().unwrap().sub_code,
+            "isv.insufficient-isv-permissions"
+        );
+    }
+
+    #[test]
+    fn returns_business_failures_without_polling() {
+        let (url, handle) = serve_biz(&[(
+            PAY,
+            r#"{"code":"40004","msg":"Business Failed","sub_code":"ACQ.PAYMENT_AUTH_CODE_INVALID","sub_msg":"支付失败，获取顾客账户信息失败"}"#,
+        )]);
+        let err = block_on(client(&url).pay_and_wait(&request(), options())).unwrap_err();
+        handle.join().unwrap();
+        assert_eq!(
+            err.biz_error().unwrap().sub_code,
+            "ACQ.PAYMENT_AUTH_CODE_INVALID"
+        );
+    }
+}
@@ -0,0 +1,521 @@
+use crate::{
+    error::AlipayResult,
+    interceptor::{AlipayRequest, Interceptor},
+    metrics::{CallRecord, Metrics},
+    rate_limit::RateLimiter,
+    response::Response,
+    retry::RetryPolicy,
+    util::sleep,
+};
+use std::{
+    fmt,
+    io::Read,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+use ureq::{Agent, Error as UreqError, Request};
+
+/// 请求体，Reader只能发送一次
+enum Body<'a> {
+    Bytes(&'a [u8]),
+    Reader(Box<dyn Read + 'a>),
+}
+
+/// 一次请求之后的处理
+enum Next {
+    Done(AlipayResult<Response>),
+    /// 等待一段时间后重试
+    Retry(Duration),
+}
+
+/// 发送已签名请求的http层，由Client和ClientWithParams共享
+#[derive(Clone)]
+pub(crate) struct Transport {
+    agent: Agent,
+    retry_policy: RetryPolicy,
+    metrics: Option<Arc<dyn Metrics>>,
+    interceptors: Vec<Arc<dyn Interceptor>>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+}
+
+impl Transport {
+    pub(crate) fn new(
+        agent: Agent,
+        retry_policy: RetryPolicy,
+        metrics: Option<Arc<dyn Metrics>>,
+        interceptors: Vec<Arc<dyn Interceptor>>,
+        rate_limiter: Option<RateLimiter>,
+    ) -> Self {
+        Transport {
+            agent,
+            retry_policy,
+            metrics,
+            interceptors,
+            rate_limiter: rate_limiter.map(Arc::new),
+        }
+    }
+
+    pub(crate) fn before_sign(&self, request: &mut AlipayRequest) -> AlipayResult<()> {
+        for interceptor in self.interceptors.iter() {
+            interceptor.before_sign(request)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn after_sign(&self, request: &AlipayRequest) -> AlipayResult<()> {
+        for interceptor in self.interceptors.iter() {
+            interceptor.after_sign(request)?;
+        }
+        Ok(())
+    }
+
+    /// 请求结束时调用拦截器，成功时调用on_response，失败时调用on_error
+    fn complete(&self, method: &str, result: AlipayResult<Response>) -> AlipayResult<Response> {
+        let result = result.and_then(|mut response| {
+            for interceptor in self.interceptors.iter() {
+                interceptor.on_response(method, &mut response)?;
+            }
+            Ok(response)
+        });
+        if let Err(err) = &result {
+            for interceptor in self.interceptors.iter() {
+                interceptor.on_error(method, err);
+            }
+        }
+        result
+    }
+
+    pub(crate) fn post(&self, url: &str) -> Request {
+        self.agent.post(url)
+    }
+
+    /// 发送请求，必要时按照重试策略重试，限流和重试前阻塞当前线程等待
+    ///
+    /// 每次重试都会重新发送同一个已签名的请求体，每次请求都会记录指标。
+    pub(crate) fn send(
+        &self,
+        method: &str,
+        biz_content: Option<&str>,
+        request: &Request,
+        body: &[u8],
+    ) -> AlipayResult<Response> {
+        let retries = self.retry_policy.retries_for(method, biz_content);
+        #[cfg(feature = "tracing")]
+        let _enter = Self::span(method, biz_content).entered();
+        let mut attempt = 0;
+        let result = loop {
+            if let Err(err) = self.acquire(method) {
+                break Err(err);
+            }
+            match self.attempt(method, attempt, retries, request, Body::Bytes(body)) {
+                Next::Done(result) => break result,
+                Next::Retry(backoff) => thread::sleep(backoff),
+            }
+            attempt += 1;
+        };
+        self.complete(method, result)
+    }
+
+    /// 异步发送请求，与send相同，但是限流和重试前的等待不会阻塞线程
+    pub(crate) async fn send_async(
+        &self,
+        method: &str,
+        biz_content: Option<&str>,
+        request: &Request,
+        body: &[u8],
+    ) -> AlipayResult<Response> {
+        let retries = self.retry_policy.retries_for(method, biz_content);
+        let send = async {
+            let mut attempt = 0;
+            loop {
+                let wait = self.reserve(method)?;
+                if !wait.is_zero() {
+                    sleep(wait).await;
+                }
+                match self.attempt(method, attempt, retries, request, Body::Bytes(body)) {
+                    Next::Done(result) => return result,
+                    Next::Retry(backoff) => sleep(backoff).await,
+                }
+                attempt += 1;
+            }
+        };
+        #[cfg(feature = "tracing")]
+        let send = tracing::Instrument::instrument(send, Self::span(method, biz_content));
+        let result = send.await;
+        self.complete(method, result)
+    }
+
+    /// 以流的方式发送请求体，请求体只能读取一次，所以不会重试
+    ///
+    /// 限流的等待不会阻塞线程，等待之后才调用prepare生成请求和请求体，
+    /// 请求体不需要在等待期间保持有效。
+    pub(crate) async fn send_reader<R, F>(&self, method: &str, prepare: F) -> AlipayResult<Response>
+    where
+        R: Read,
+        F: FnOnce() -> AlipayResult<(Request, R)>,
+    {
+        let send = async {
+            let wait = self.reserve(method)?;
+            if !wait.is_zero() {
+                sleep(wait).await;
+            }
+            let (request, reader) = prepare()?;
+            match self.attempt(method, 0, 0, &request, Body::Reader(Box::new(reader))) {
+                Next::Done(result) => result,
+                Next::Retry(_) => unreachable!("streaming requests are never retried"),
+            }
+        };
+        #[cfg(feature = "tracing")]
+        let send = tracing::Instrument::instrument(send, Self::span(method, None));
+        let result = send.await;
+        self.complete(method, result)
+    }
+
+    #[cfg(feature = "tracing")]
+    fn span(method: &str, biz_content: Option<&str>) -> tracing::Span {
+        let span = tracing::info_span!("alipay_request", method = %method);
+        if let Some(content) = biz_content {
+            span.in_scope(|| {
+                tracing::debug!(biz_content = %crate::metrics::redact(content), "send request");
+            });
+        }
+        span
+    }
+
+    /// 预留一个令牌，返回发送前需要等待的时间，重试也会占用令牌
+    fn reserve(&self, method: &str) -> AlipayResult<Duration> {
+        match &self.rate_limiter {
+            Some(rate_limiter) => rate_limiter.reserve(method),
+            None => Ok(Duration::ZERO),
+        }
+    }
+
+    /// 获取令牌，令牌不足时阻塞当前线程等待
+    fn acquire(&self, method: &str) -> AlipayResult<()> {
+        match &self.rate_limiter {
+            Some(rate_limiter) => rate_limiter.acquire(method),
+            None => Ok(()),
+        }
+    }
+
+    /// 发送一次请求，根据结果和剩余的重试次数决定是否重试
+    fn attempt(
+        &self,
+        method: &str,
+        attempt: u32,
+        retries: u32,
+        request: &Request,
+        body: Body<'_>,
+    ) -> Next {
+        let start = Instant::now();
+        let result = match body {
+            Body::Bytes(bytes) => request.clone().send_bytes(bytes),
+            Body::Reader(reader) => request.clone().send(reader),
+        };
+        let mut record = CallRecord {
+            method: method.to_owned(),
+            attempt,
+            latency: start.elapsed(),
+            status: None,
+            code: None,
+            sub_code: None,
+            trace_id: None,
+            error: None,
+        };
+        let can_retry = attempt < retries;
+        match result {
+            Ok(res) => {
+                let res = match Response::new(res) {
+                    Ok(res) => res,
+                    Err(err) => {
+                        record.error = Some(err.to_string());
+                        self.observe(&record);
+                        return Next::Done(Err(err));
+                    }
+                };
+                record.status = Some(res.status());
+                record.trace_id = res.trace_id().map(|v| v.to_owned());
+                let mut system_failure = false;
+                if let Some((code, sub_code)) = res.gateway_code() {
+                    system_failure = RetryPolicy::is_system_failure(&code, &sub_code);
+                    record.code = Some(code);
+                    record.sub_code = Some(sub_code).filter(|v| !v.is_empty());
+                }
+                self.observe(&record);
+                if !(can_retry && system_failure) {
+                    return Next::Done(Ok(res));
+                }
+            }
+            Err(err) => {
+                if let UreqError::Status(status, _) = &err {
+                    record.status = Some(*status);
+                }
+                record.error = Some(err.to_string());
+                self.observe(&record);
+                if !(can_retry && matches!(err, UreqError::Transport(_))) {
+                    return Next::Done(Err(err.into()));
+                }
+            }
+        }
+        Next::Retry(self.retry_policy.backoff(attempt))
+    }
+
+    fn observe(&self, record: &CallRecord) {
+        #[cfg(feature = "tracing")]
+        {
+            let latency_ms = record.latency.as_millis() as u64;
+            if let Some(error) = &record.error {
+                tracing::warn!(
+                    attempt = record.attempt,
+                    latency_ms,
+                    status = ?record.status,
+                    error = %error,
+                    "request failed"
+                );
+            } else {
+                tracing::info!(
+                    attempt = record.attempt,
+                    latency_ms,
+                    status = ?record.status,
+                    code = ?record.code,
+                    sub_code = ?record.sub_code,
+                    trace_id = ?record.trace_id,
+                    "request finished"
+                );
+            }
+        }
+        if let Some(metrics) = &self.metrics {
+            metrics.record(record);
+        }
+    }
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        Transport::new(Agent::new(), RetryPolicy::default(), None, Vec::new(), None)
+    }
+}
+
+impl fmt::Debug for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transport")
+            .field("agent", &self.agent)
+            .field("retry_policy", &self.retry_policy)
+            .field("metrics", &self.metrics.is_some())
+            .field("interceptors", &self.interceptors.len())
+            .field("rate_limit", &self.rate_limiter.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::AlipayError,
+        test_util::{http_response, serve, serve_raw},
+        CallRecord,
+    };
+    use std::{net::TcpListener, sync::Mutex};
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl Interceptor for Recorder {
+        fn on_response(&self, method: &str, response: &mut Response) -> AlipayResult<()> {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("response {} {}", method, response.status()));
+            Ok(())
+        }
+        fn on_error(&self, method: &str, _error: &AlipayError) {
+            self.0.lock().unwrap().push(format!("error {}", method));
+        }
+    }
+
+    fn transport(recorder: Arc<Recorder>) -> Transport {
+        Transport::new(
+            Agent::new(),
+            RetryPolicy::none(),
+            None,
+            vec![recorder],
+            None,
+        )
+    }
+
+    #[test]
+    fn hooks_run_on_success() {
+        let recorder = Arc::new(Recorder::default());
+        let transport = transport(recorder.clone());
+        let (url, handle) = serve(&[("200 OK", "{}")]);
+        let request = transport.post(&url);
+        let result = transport.send("alipay.trade.query", None, &request, b"a=1");
+        handle.join().unwrap();
+        assert!(result.is_ok());
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            ["response alipay.trade.query 200"]
+        );
+    }
+
+    #[test]
+    fn hooks_run_on_failure() {
+        let recorder = Arc::new(Recorder::default());
+        let transport = transport(recorder.clone());
+        let (url, handle) = serve(&[("500 Internal Server Error", "{}")]);
+        let result =
+            futures::executor::block_on(transport.send_reader("alipay.trade.query", || {
+                Ok((transport.post(&url), &b"a=1"[..]))
+            }));
+        handle.join().unwrap();
+        assert!(result.is_err());
+        assert_eq!(*recorder.0.lock().unwrap(), ["error alipay.trade.query"]);
+    }
+
+    #[test]
+    fn hooks_run_on_connection_error() {
+        let recorder = Arc::new(Recorder::default());
+        let transport = transport(recorder.clone());
+        let url = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}/gateway.do", listener.local_addr().unwrap())
+        };
+        let request = transport.post(&url);
+        assert!(transport
+            .send("alipay.trade.pay", None, &request, b"a=1")
+            .is_err());
+        assert_eq!(*recorder.0.lock().unwrap(), ["error alipay.trade.pay"]);
+    }
+
+    const SYSTEM_ERROR: &str = r#"{"alipay_trade_query_response":{"code":"20000","msg":"Service Currently Unavailable","sub_code":"aop.unknow-error"},"sign":"x"}"#;
+    const SUCCESS: &str =
+        r#"{"alipay_trade_query_response":{"code":"10000","msg":"Success"},"sign":"x"}"#;
+
+    fn retry_transport() -> Transport {
+        let policy = RetryPolicy::new(2).initial_backoff(Duration::from_millis(1));
+        Transport::new(Agent::new(), policy, None, Vec::new(), None)
+    }
+
+    #[test]
+    fn async_send_retries_system_failures() {
+        let transport = retry_transport();
+        let (url, handle) = serve(&[("200 OK", SYSTEM_ERROR), ("200 OK", SUCCESS)]);
+        let request = transport.post(&url);
+        let response = futures::executor::block_on(transport.send_async(
+            "alipay.trade.query",
+            None,
+            &request,
+            b"a=1",
+        ))
+        .unwrap();
+        handle.join().unwrap();
+        assert_eq!(
+            response.gateway_code(),
+            Some(("10000".to_owned(), String::new()))
+        );
+    }
+
+    #[test]
+    fn non_idempotent_requests_are_not_retried() {
+        let transport = retry_transport();
+        // 只回复一次，重试时连接会失败
+        let (url, handle) = serve(&[("200 OK", SYSTEM_ERROR)]);
+        let request = transport.post(&url);
+        let response = futures::executor::block_on(transport.send_async(
+            "alipay.trade.pay",
+            Some(r#"{"subject":"iphone"}"#),
+            &request,
+            b"a=1",
+        ))
+        .unwrap();
+        handle.join().unwrap();
+        assert_eq!(response.gateway_code().unwrap().0, "20000");
+    }
+
+    #[derive(Default)]
+    struct Records(Mutex<Vec<CallRecord>>);
+
+    impl Metrics for Records {
+        fn record(&self, record: &CallRecord) {
+            self.0.lock().unwrap().push(record.clone());
+        }
+    }
+
+    fn metrics_transport(records: Arc<Records>) -> Transport {
+        let policy = RetryPolicy::new(2).initial_backoff(Duration::from_millis(1));
+        Transport::new(Agent::new(), policy, Some(records), Vec::new(), None)
+    }
+
+    #[test]
+    fn metrics_record_every_attempt() {
+        let records = Arc::new(Records::default());
+        let transport = metrics_transport(records.clone());
+        let (url, handle) = serve_raw(vec![
+            http_response("200 OK", &[("trace_id", "0b6d1a4e1")], SYSTEM_ERROR),
+            http_response("200 OK", &[("alipay-trace-id", "0b6d1a4e2")], SUCCESS),
+        ]);
+        let request = transport.post(&url);
+        transport
+            .send("alipay.trade.query", None, &request, b"a=1")
+            .unwrap();
+        handle.join().unwrap();
+
+        let records = records.0.lock().unwrap();
+        assert_eq!(records.len(), 2);
+        let (first, second) = (&records[0], &records[1]);
+        assert_eq!(first.method, "alipay.trade.query");
+        assert_eq!(first.attempt, 0);
+        assert_eq!(first.status, Some(200));
+        assert_eq!(first.code.as_deref(), Some("20000"));
+        assert_eq!(first.sub_code.as_deref(), Some("aop.unknow-error"));
+        assert_eq!(first.trace_id.as_deref(), Some("0b6d1a4e1"));
+        assert!(!first.is_success());
+        assert_eq!(second.attempt, 1);
+        assert_eq!(second.code.as_deref(), Some("10000"));
+        assert_eq!(second.sub_code, None);
+        assert_eq!(second.trace_id.as_deref(), Some("0b6d1a4e2"));
+        assert!(second.is_success());
+        assert!(records.iter().all(|record| !record.latency.is_zero()));
+    }
+
+    #[test]
+    fn metrics_record_failed_attempts() {
+        let records = Arc::new(Records::default());
+        let transport = metrics_transport(records.clone());
+        let (url, handle) = serve(&[("500 Internal Server Error", "{}")]);
+        let request = transport.post(&url);
+        let result = futures::executor::block_on(transport.send_async(
+            "alipay.trade.query",
+            None,
+            &request,
+            b"a=1",
+        ));
+        handle.join().unwrap();
+        assert!(result.is_err());
+        {
+            // 500不是网络错误，不会重试
+            let records = records.0.lock().unwrap();
+            assert_eq!(records.len(), 1);
+            assert_eq!(records[0].status, Some(500));
+            assert!(records[0].error.is_some());
+        }
+
+        records.0.lock().unwrap().clear();
+        let url = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}/gateway.do", listener.local_addr().unwrap())
+        };
+        let request = transport.post(&url);
+        assert!(transport
+            .send("alipay.trade.query", None, &request, b"a=1")
+            .is_err());
+        let records = records.0.lock().unwrap();
+        let attempts: Vec<u32> = records.iter().map(|record| record.attempt).collect();
+        assert_eq!(attempts, [0, 1, 2]);
+        assert!(records
+            .iter()
+            .all(|record| record.status.is_none() && record.error.is_some()));
+    }
+}
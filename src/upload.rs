@@ -0,0 +1,377 @@
+use crate::{
+    error::{AlipayError, AlipayResult},
+    interceptor::AlipayRequest,
+    response::Response,
+    transport::Transport,
+    AlipayParams, AlipayValue,
+};
+use multipart::client::lazy::{Multipart, PreparedFields};
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+use ureq::Request;
+
+enum Source<'a> {
+    Bytes(&'a [u8]),
+    Reader(Box<dyn Read + Send + 'a>),
+    Path(PathBuf),
+}
+
+struct FilePart<'a> {
+    name: String,
+    file_name: Option<String>,
+    content_type: Option<String>,
+    source: Source<'a>,
+}
+
+/// 文件上传的表单
+///
+/// 每个文件是一个multipart字段，content_type为None时使用application/octet-stream，
+/// 通过路径添加的文件会根据扩展名判断类型。
+/// 只包含内存中的文件时请求体会被缓存，失败时可以按照重试策略重试；
+/// 包含reader或者路径时以流的方式发送，不会重试。
+///
+/// ```rust,no_run
+/// use alipay_rs::{Cli, UploadForm};
+///
+/// # async fn upload(client: &alipay_rs::Client) -> alipay_rs::error::AlipayResult<()> {
+/// let mut form = UploadForm::new();
+/// form.path("app_version_package", "./dist.zip", Some("application/zip"))
+///     .bytes("image_content", "logo.png", &[0u8; 16], Some("image/png"));
+/// let data: serde_json::Value = client
+///     .post_multipart(
+///         "alipay.open.mini.version.upload",
+///         [("app_version", "0.0.1"), ("bundle_id", "com.alipay.alipaywallet")],
+///         form,
+///     )
+///     .await?
+///     .into_json()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct UploadForm<'a> {
+    files: Vec<FilePart<'a>>,
+}
+
+impl<'a> UploadForm<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// 添加内存中的文件
+    pub fn bytes(
+        &mut self,
+        name: &str,
+        file_name: &str,
+        content: &'a [u8],
+        content_type: Option<&str>,
+    ) -> &mut Self {
+        self.push(name, Some(file_name), content_type, Source::Bytes(content))
+    }
+    /// 添加reader，发送时才会读取
+    pub fn reader<R: Read + Send + 'a>(
+        &mut self,
+        name: &str,
+        file_name: &str,
+        reader: R,
+        content_type: Option<&str>,
+    ) -> &mut Self {
+        self.push(
+            name,
+            Some(file_name),
+            content_type,
+            Source::Reader(Box::new(reader)),
+        )
+    }
+    /// 添加文件路径，发送时才会打开文件，文件名为路径中的文件名
+    pub fn path<P: AsRef<Path>>(
+        &mut self,
+        name: &str,
+        path: P,
+        content_type: Option<&str>,
+    ) -> &mut Self {
+        let path = path.as_ref().to_path_buf();
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        self.push(name, file_name.as_deref(), content_type, Source::Path(path))
+    }
+
+    fn push(
+        &mut self,
+        name: &str,
+        file_name: Option<&str>,
+        content_type: Option<&str>,
+        source: Source<'a>,
+    ) -> &mut Self {
+        self.files.push(FilePart {
+            name: name.to_owned(),
+            file_name: file_name.map(|v| v.to_owned()),
+            content_type: content_type.map(|v| v.to_owned()),
+            source,
+        });
+        self
+    }
+
+    fn is_buffered(&self) -> bool {
+        self.files
+            .iter()
+            .all(|file| matches!(file.source, Source::Bytes(_)))
+    }
+}
+
+/// 把参数转换为表单中的文本字段，字符串直接使用，其他类型转换为json
+pub(crate) fn text_params<T: AlipayParams>(params: T) -> AlipayResult<Vec<(String, String)>> {
+    params.validate()?;
+    let params = match params.to_alipay_value() {
+        AlipayValue::Null => return Ok(Vec::new()),
+        value => value
+            .into_object()
+            .ok_or_else(|| AlipayError::new("upload params must be an object"))?,
+    };
+    let mut fields = Vec::with_capacity(params.len());
+    for (key, val) in params {
+        match val {
+            AlipayValue::Null => {}
+            AlipayValue::String(val) => fields.push((key, val)),
+            val => fields.push((key, serde_json::to_string(&val)?)),
+        }
+    }
+    Ok(fields)
+}
+
+/// 发送已签名的上传请求
+///
+/// text_keys中的参数作为文本字段放在请求体中，其余的公共参数和sign放在url中。
+pub(crate) async fn send(
+    transport: &Transport,
+    request: AlipayRequest,
+    text_keys: &[String],
+    form: UploadForm<'_>,
+) -> AlipayResult<Response> {
+    let method = request.method();
+    if form.is_buffered() {
+        let (http_request, body) = buffer(transport, &request, text_keys, form)?;
+        transport
+            .send_async(method, None, &http_request, &body)
+            .await
+    } else {
+        // 请求体只能读取一次，限流等待之后才打开文件、生成请求体
+        transport
+            .send_reader(method, || prepare(transport, &request, text_keys, form))
+            .await
+    }
+}
+
+/// 只包含内存中的文件时缓存请求体，失败时可以重试
+fn buffer(
+    transport: &Transport,
+    request: &AlipayRequest,
+    text_keys: &[String],
+    form: UploadForm<'_>,
+) -> AlipayResult<(Request, Vec<u8>)> {
+    let (http_request, mut fields) = prepare(transport, request, text_keys, form)?;
+    let mut body = Vec::new();
+    fields.read_to_end(&mut body)?;
+    Ok((http_request, body))
+}
+
+fn prepare<'a>(
+    transport: &Transport,
+    request: &AlipayRequest,
+    text_keys: &[String],
+    form: UploadForm<'a>,
+) -> AlipayResult<(Request, PreparedFields<'a>)> {
+    let (fields, query): (Vec<_>, Vec<_>) = request
+        .params
+        .iter()
+        .partition(|(key, _)| text_keys.contains(key));
+    let url = format!("{}?{}", request.url, serde_urlencoded::to_string(&query)?);
+
+    let mut multi = Multipart::new();
+    for (key, val) in fields {
+        multi.add_text(key.clone(), val.clone());
+    }
+    for file in form.files {
+        let mime = match &file.content_type {
+            Some(content_type) => Some(content_type.parse().map_err(|_| {
+                AlipayError::new(format!("invalid content type: {}", content_type))
+            })?),
+            None => None,
+        };
+        match file.source {
+            Source::Bytes(content) => {
+                multi.add_stream(file.name, content, file.file_name, mime);
+            }
+            Source::Reader(reader) => {
+                multi.add_stream(file.name, reader, file.file_name, mime);
+            }
+            Source::Path(path) => {
+                let mime = mime.or_else(|| mime_guess::from_path(&path).first());
+                multi.add_stream(file.name, File::open(path)?, file.file_name, mime);
+            }
+        }
+    }
+    let fields = multi.prepare()?;
+    let http_request = transport.post(&url).set(
+        "Content-Type",
+        &format!("multipart/form-data; boundary={}", fields.boundary()),
+    );
+    Ok((http_request, fields))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_util::{builder, serve, Captured},
+        Cli, RateLimit, Sign,
+    };
+    use futures::{executor::block_on, future};
+    use std::{
+        fs,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    const METHOD: &str = "alipay.open.mini.version.upload";
+    const SUCCESS: &str = r#"{"alipay_open_mini_version_upload_response":{"code":"10000","msg":"Success"},"sign":"x"}"#;
+
+    /// 记录待签名的字符串
+    #[derive(Clone, Default)]
+    struct Signed(Arc<Mutex<Vec<String>>>);
+
+    impl Sign for Signed {
+        fn sign(&self, params: &str) -> AlipayResult<String> {
+            self.0.lock().unwrap().push(params.to_owned());
+            Ok("signature".to_owned())
+        }
+        fn verify(&self, _source: &str, _signature: &str) -> AlipayResult<bool> {
+            Ok(true)
+        }
+    }
+
+    fn params() -> [(&'static str, &'static str); 2] {
+        [
+            ("app_version", "0.0.1"),
+            ("bundle_id", "com.alipay.alipaywallet"),
+        ]
+    }
+
+    fn query(request: &Captured) -> Vec<(String, String)> {
+        let target = request.head.split(' ').nth(1).unwrap();
+        let (_, query) = target.split_once('?').unwrap();
+        serde_urlencoded::from_str(query).unwrap()
+    }
+
+    fn text_field(name: &str, value: &str) -> String {
+        format!(
+            "Content-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+            name, value
+        )
+    }
+
+    #[test]
+    fn text_params_are_signed_and_sent_in_the_body() {
+        let (url, handle) = serve(&[("200 OK", SUCCESS)]);
+        let signed = Signed::default();
+        let client = builder(&url).signer(signed.clone()).finish();
+        let mut form = UploadForm::new();
+        form.bytes("image_content", "logo.png", b"png bytes", Some("image/png"));
+        block_on(client.post_multipart(METHOD, params(), form)).unwrap();
+        let request = handle.join().unwrap().remove(0);
+
+        let body = String::from_utf8_lossy(&request.body);
+        assert!(body.contains(&text_field("app_version", "0.0.1")));
+        assert!(body.contains(&text_field("bundle_id", "com.alipay.alipaywallet")));
+
+        let signed = signed.0.lock().unwrap();
+        assert!(signed[0].contains("app_version=0.0.1"));
+        assert!(signed[0].contains("bundle_id=com.alipay.alipaywallet"));
+        assert!(signed[0].contains(&format!("method={}", METHOD)));
+
+        let query = query(&request);
+        let value = |name: &str| {
+            query
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, val)| val.as_str())
+        };
+        assert_eq!(value("method"), Some(METHOD));
+        assert_eq!(value("app_id"), Some("2021000000000000"));
+        assert_eq!(value("sign"), Some("signature"));
+        assert_eq!(value("app_version"), None);
+        assert_eq!(value("bundle_id"), None);
+    }
+
+    #[test]
+    fn bytes_readers_and_paths_are_sent() {
+        let path =
+            std::env::temp_dir().join(format!("alipay-rs-upload-{}.zip", std::process::id()));
+        fs::write(&path, b"zip bytes").unwrap();
+        let (url, handle) = serve(&[("200 OK", SUCCESS)]);
+        let client = builder(&url).finish();
+        let mut form = UploadForm::new();
+        form.bytes("image_content", "logo.png", b"png bytes", Some("image/png"))
+            .reader("template", "template.json", &b"{}"[..], None)
+            .path("app_version_package", &path, None);
+        let result = block_on(client.post_multipart(METHOD, params(), form));
+        fs::remove_file(&path).unwrap();
+        result.unwrap();
+        let request = handle.join().unwrap().remove(0);
+
+        assert!(request
+            .head
+            .contains("Content-Type: multipart/form-data; boundary="));
+        let body = String::from_utf8_lossy(&request.body);
+        assert!(body.contains(&text_field("app_version", "0.0.1")));
+        for (name, file_name, content_type, content) in [
+            ("image_content", "logo.png", "image/png", "png bytes"),
+            (
+                "template",
+                "template.json",
+                "application/octet-stream",
+                "{}",
+            ),
+            (
+                "app_version_package",
+                path.file_name().unwrap().to_str().unwrap(),
+                "application/zip",
+                "zip bytes",
+            ),
+        ] {
+            let part = format!(
+                "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n{}\r\n",
+                name, file_name, content_type, content
+            );
+            assert!(body.contains(&part), "missing {} in {}", name, body);
+        }
+    }
+
+    #[test]
+    fn streamed_uploads_wait_without_blocking_the_thread() {
+        let (url, handle) = serve(&[("200 OK", SUCCESS), ("200 OK", SUCCESS)]);
+        let client = builder(&url)
+            .rate_limit(RateLimit::new(1).burst(1))
+            .finish();
+        let upload = || {
+            let mut form = UploadForm::new();
+            form.reader("template", "template.json", &b"{}"[..], None);
+            client.post_multipart(METHOD, (), form)
+        };
+        let start = Instant::now();
+        // 第二个上传需要等待令牌，等待期间同一个线程上的其他future可以继续执行
+        let (first, second, other) =
+            block_on(future::join3(upload(), upload(), async { start.elapsed() }));
+        handle.join().unwrap();
+        assert!(first.is_ok() && second.is_ok());
+        assert!(
+            other < Duration::from_millis(500),
+            "blocked for {:?}",
+            other
+        );
+        assert!(start.elapsed() >= Duration::from_millis(500));
+    }
+}
@@ -1,4 +1,6 @@
-use crate::error::AlipayResult;
+use crate::{error::AlipayResult, AlipayParams};
+use serde_json::{Map, Value};
+use std::time::Duration;
 
 fn get_hour_min_sec(timestamp: u64) -> (i32, i32, i32) {
     let hour = ((timestamp % (24 * 3600)) / 3600 + 8) % 24;
@@ -60,3 +62,43 @@ pub fn datetime() -> AlipayResult<String> {
         "{year}-{moth:>02}-{day:>02} {h:>02}:{m:>02}:{s:>02}",
     ))
 }
+
+/// 生成请求号，用于out_request_no等需要唯一的参数
+///
+/// 由毫秒时间戳、进程id和自增序号组成，同一进程内不会重复。
+pub fn request_no() -> String {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::SystemTime;
+    static SEQ: AtomicU32 = AtomicU32::new(0);
+    let millis = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let seq = SEQ.fetch_add(1, Ordering::Relaxed) % 10000;
+    format!("{millis}{:05}{seq:04}", std::process::id() % 100000)
+}
+
+/// 异步等待，不依赖具体的异步运行时，也不会阻塞线程
+pub(crate) async fn sleep(duration: Duration) {
+    futures_timer::Delay::new(duration).await
+}
+
+/// 校验公共参数并转换为json对象，参数不是对象时没有公共参数
+pub(crate) fn public_params<T: AlipayParams>(params: T) -> AlipayResult<Map<String, Value>> {
+    params.validate()?;
+    match params.to_alipay_value().to_json_value() {
+        Value::Object(params) => Ok(params),
+        _ => Ok(Map::new()),
+    }
+}
+
+/// 校验参数并转换为biz_content，参数为Null时没有biz_content
+pub(crate) fn biz_content<T: AlipayParams>(biz_content: T) -> AlipayResult<Option<String>> {
+    biz_content.validate()?;
+    let biz_content = biz_content.to_alipay_value();
+    if biz_content.is_null() {
+        Ok(None)
+    } else {
+        Ok(Some(serde_json::to_string(&biz_content.to_json_value())?))
+    }
+}